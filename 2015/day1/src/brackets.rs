@@ -0,0 +1,195 @@
+//! Provides an analysis of the directions read as a string of brackets.
+//!
+//! A character that goes up one floor opens a bracket and one that goes down one floor closes it.
+//! Any other character is passed over, though it still takes up a position. The analysis is made
+//! in the same single pass as the rest of the trip: a closing bracket matches the most recent
+//! bracket still open, so only the positions of the brackets still open and of the closing
+//! brackets left unmatched need to be kept. They are kept as runs of consecutive positions, so
+//! directions that open or close many brackets in a row take no more room than a single bracket.
+
+/// Balanced is a run of positions whose brackets all match each other.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Balanced {
+    /// The position of the first character of the run.
+    pub start: u64,
+    /// The number of positions in the run.
+    pub length: u64,
+}
+
+/// Brackets matches up the brackets of the directions one character at a time.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Brackets {
+    /// The largest number of brackets open at once.
+    pub max_depth: u64,
+    /// The number of closing brackets that had nothing to match.
+    pub unmatched: u64,
+    /// The positions of the closing brackets that had nothing to match, as the first position and
+    /// length of each run of consecutive positions.
+    pub unmatched_runs: Vec<(u64, u64)>,
+    /// The number of brackets still open.
+    pub open: u64,
+    /// The positions of the brackets still open, oldest first, as the first position and length of
+    /// each run of consecutive positions.
+    pub open_runs: Vec<(u64, u64)>,
+    /// The longest balanced run, the first one if several are as long.
+    pub longest_balanced: Option<Balanced>,
+    opening: u64,
+    closing: u64,
+    /// The position of the last unmatched closing bracket, which no balanced run can span.
+    barrier: u64,
+}
+
+/// Extends the last of `runs` by `position` if it follows on from it, or starts a new run.
+fn extend(runs: &mut Vec<(u64, u64)>, position: u64) {
+    match runs.last_mut() {
+        Some(&mut (first, ref mut length)) if first + *length == position => *length += 1,
+        _ => runs.push((position, 1)),
+    }
+}
+
+impl Brackets {
+    /// Follows the character at `position`, which moves by `delta` floors.
+    pub fn step(&mut self, position: u64, delta: Option<i64>) {
+        match delta {
+            Some(1) => {
+                self.opening += 1;
+                self.open += 1;
+                self.max_depth = self.max_depth.max(self.open);
+                extend(&mut self.open_runs, position);
+            },
+            Some(-1) => {
+                self.closing += 1;
+                if self.open == 0 {
+                    self.unmatched += 1;
+                    extend(&mut self.unmatched_runs, position);
+                    self.barrier = position;
+                    return
+                }
+
+                self.open -= 1;
+                let last = self.open_runs.len() - 1;
+                self.open_runs[last].1 -= 1;
+                if self.open_runs[last].1 == 0 {
+                    self.open_runs.pop();
+                }
+
+                // The run ends here and starts after the bracket still open around it.
+                let before = self.open_runs.last().map(|&(first, length)| first + length - 1).unwrap_or(self.barrier);
+                let balanced = Balanced { start: before + 1, length: position - before };
+                if self.longest_balanced.is_none_or(|longest| balanced.length > longest.length) {
+                    self.longest_balanced = Some(balanced);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    /// Returns the fewest brackets that need to be flipped for every bracket to be matched, or
+    /// `None` if there is an odd number of brackets.
+    pub fn flips_to_balance(&self) -> Option<u64> {
+        let (unmatched, open) = (self.unmatched, self.open);
+        if !(unmatched + open).is_multiple_of(2) {
+            return None;
+        }
+
+        // Unmatched closing brackets are fixed by flipping every other one, and likewise for the
+        // brackets left open. An odd one out of each is fixed by flipping both.
+        Some(unmatched.div_ceil(2) + open.div_ceil(2))
+    }
+
+    /// Returns the fewest brackets that need to be flipped for the brackets to end on `floor`, or
+    /// `None` if it cannot be reached.
+    pub fn flips_to_floor(&self, floor: i64) -> Option<u64> {
+        let difference = self.opening as i64 - self.closing as i64 - floor;
+        if difference % 2 != 0 {
+            return None;
+        }
+
+        // Each flip moves the end by two floors.
+        let flips = difference.unsigned_abs() / 2;
+        let available = if difference > 0 { self.opening } else { self.closing };
+        if flips > available {
+            return None;
+        }
+        Some(flips)
+    }
+}
+
+#[test]
+fn brackets_test() {
+    let mut brackets = Brackets::default();
+    for (i, c) in "())((x())(".bytes().enumerate() {
+        let delta = match c {
+            b'(' => Some(1),
+            b')' => Some(-1),
+            _ => None,
+        };
+        brackets.step(i as u64 + 1, delta);
+    }
+
+    assert_eq!(3, brackets.max_depth);
+    assert_eq!((1, 2), (brackets.unmatched, brackets.open));
+    assert_eq!(vec!((4, 1), (10, 1)), brackets.open_runs);
+    assert_eq!(Some(Balanced { start: 5, length: 5 }), brackets.longest_balanced);
+    assert_eq!(None, brackets.flips_to_balance());
+
+    assert_eq!(Some(0), brackets.flips_to_floor(1));
+    assert_eq!(Some(1), brackets.flips_to_floor(3));
+    assert_eq!(Some(2), brackets.flips_to_floor(-3));
+    assert_eq!(None, brackets.flips_to_floor(0));
+    assert_eq!(Some(4), brackets.flips_to_floor(9));
+    assert_eq!(None, brackets.flips_to_floor(11));
+    assert_eq!(Some(5), brackets.flips_to_floor(-9));
+    assert_eq!(None, brackets.flips_to_floor(-11));
+
+    // Brackets opened in a row are kept as a single run.
+    let mut brackets = Brackets::default();
+    for (i, c) in "((((()(((".bytes().enumerate() {
+        brackets.step(i as u64 + 1, Some(if c == b'(' { 1 } else { -1 }));
+    }
+    assert_eq!((7, 7), (brackets.max_depth, brackets.open));
+    assert_eq!(vec!((1, 4), (7, 3)), brackets.open_runs);
+    assert_eq!(Some(Balanced { start: 5, length: 2 }), brackets.longest_balanced);
+
+    assert_eq!(None, Brackets::default().longest_balanced);
+    assert_eq!(Some(0), Brackets::default().flips_to_balance());
+}
+
+#[test]
+fn positions_test() {
+    let cases = vec!(
+        (")(()((", vec!((1, 1)), vec!((2, 1), (5, 2))),
+        ("))x)(", vec!((1, 2), (4, 1)), vec!((5, 1))),
+        ("(())", vec!(), vec!()),
+    );
+
+    for (directions, unmatched, open) in cases {
+        let mut brackets = Brackets::default();
+        for (i, c) in directions.bytes().enumerate() {
+            let delta = match c {
+                b'(' => Some(1),
+                b')' => Some(-1),
+                _ => None,
+            };
+            brackets.step(i as u64 + 1, delta);
+        }
+
+        assert_eq!(unmatched, brackets.unmatched_runs, "{}", directions);
+        assert_eq!(open, brackets.open_runs, "{}", directions);
+    }
+}
+
+#[test]
+fn flips_to_balance_test() {
+    let cases = vec!(("()", 0), (")(", 2), ("))((", 2), (")))(", 3), ("((((", 2), ("(((()", 1));
+
+    for (directions, flips) in cases {
+        let mut brackets = Brackets::default();
+        for (i, c) in directions.bytes().enumerate() {
+            brackets.step(i as u64 + 1, Some(if c == b'(' { 1 } else { -1 }));
+        }
+
+        let expected = if directions.len() % 2 == 0 { Some(flips) } else { None };
+        assert_eq!(expected, brackets.flips_to_balance(), "{}", directions);
+    }
+}
@@ -0,0 +1,245 @@
+//! Provides a building of several elevators following directions at the same time.
+//!
+//! Either each elevator has a stream of directions of its own, and the elevators take a character
+//! from their streams in turn, or the elevators share a single stream and take its characters in
+//! turn. The building may have a lowest and a highest floor, and an elevator told to go beyond
+//! them either stops at the last floor or fails, depending on the Limit. Each elevator keeps track
+//! of its own trip, see `elevator::Elevator`, and when the elevators keep a timeline the building
+//! also keeps the order in which they first reached each floor.
+
+use std::io;
+use std::io::prelude::*;
+
+use elevator::{Alphabet, Elevator, Tracking, CHUNK_SIZE};
+
+/// Limit is what happens to an elevator told to go beyond the lowest or highest floor.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Limit {
+    /// The elevator goes as far as it can and waits there.
+    Clamp,
+    /// The directions fail.
+    Fail,
+}
+
+/// Event is an elevator reaching a floor for the first time.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Event {
+    /// The index of the elevator.
+    pub elevator: usize,
+    pub floor: i64,
+    /// The position in the elevator's own directions at which it reached the floor.
+    pub position: u64,
+}
+
+/// Building holds the elevators and the floors they may reach.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Building {
+    pub elevators: Vec<Elevator>,
+    pub lowest: Option<i64>,
+    pub highest: Option<i64>,
+    pub limit: Limit,
+    /// The first time each elevator reached each floor, in the order it happened. Only kept when
+    /// the elevators keep a timeline.
+    pub events: Vec<Event>,
+}
+
+impl Building {
+    /// Creates a building of `count` elevators, all on the ground floor and keeping track of what
+    /// `tracking` asks for.
+    ///
+    /// Fails if there are no elevators, or if the ground floor lies outside of the building.
+    pub fn new(count: usize, lowest: Option<i64>, highest: Option<i64>, limit: Limit, tracking: Tracking) -> Result<Building, String> {
+        if count == 0 {
+            return Err("a building needs at least one elevator".to_string());
+        }
+        if lowest.is_some_and(|lowest| lowest > 0) || highest.is_some_and(|highest| highest < 0) {
+            return Err("the ground floor should lie between the lowest and highest floors".to_string());
+        }
+
+        Ok(Building { elevators: vec!(Elevator::new(tracking); count), lowest, highest, limit, events: Vec::new() })
+    }
+
+    /// Moves the elevator at `index` by `delta` floors, as `Elevator::step` does.
+    ///
+    /// Fails if the elevator would go beyond the building and the limit is `Limit::Fail`, or if its
+    /// floor would go out of range.
+    pub fn step(&mut self, index: usize, delta: Option<i64>) -> Result<(), String> {
+        let elevator = &mut self.elevators[index];
+
+        let delta = match delta {
+            Some(delta) => {
+                let floor = elevator.floor.checked_add(delta)
+                    .ok_or(format!("elevator {} goes out of range at position {}", index + 1, elevator.position + 1))?;
+                let allowed = floor.max(self.lowest.unwrap_or(i64::MIN)).min(self.highest.unwrap_or(i64::MAX));
+                if allowed != floor && self.limit == Limit::Fail {
+                    return Err(format!("elevator {} would go to floor {} at position {}",
+                        index + 1, floor, elevator.position + 1));
+                }
+
+                // An elevator already waiting at the last floor does not move at all.
+                if allowed == elevator.floor && delta != 0 { None } else { Some(allowed - elevator.floor) }
+            },
+            None => None,
+        };
+
+        let first = match (delta, &elevator.timeline) {
+            (Some(delta), Some(timeline)) => timeline.visits(elevator.floor + delta).is_none(),
+            _ => false,
+        };
+        elevator.step(delta).map_err(|e| e.to_string())?;
+        if first {
+            self.events.push(Event { elevator: index, floor: elevator.floor, position: elevator.position });
+        }
+        Ok(())
+    }
+
+    /// Follows a stream of directions for each elevator, the elevators taking a character in
+    /// turn until every stream has ended.
+    ///
+    /// Fails if there is not one stream for each elevator, if a stream cannot be read, or if an
+    /// elevator goes beyond the building.
+    pub fn follow_each<R: Read>(&mut self, inputs: Vec<R>, alphabet: &Alphabet) -> Result<(), String> {
+        if inputs.len() != self.elevators.len() {
+            return Err(format!("there are {} streams of directions for {} elevators",
+                inputs.len(), self.elevators.len()));
+        }
+
+        let mut streams: Vec<Option<io::Bytes<io::BufReader<R>>>> = inputs.into_iter()
+            .map(|input| Some(io::BufReader::with_capacity(CHUNK_SIZE, input).bytes()))
+            .collect();
+        let mut offsets = vec!(0; streams.len());
+
+        while streams.iter().any(Option::is_some) {
+            for index in 0..streams.len() {
+                let next = match streams[index] {
+                    Some(ref mut bytes) => next_move(bytes, &mut offsets[index], alphabet)
+                        .map_err(|e| format!("elevator {}: {}", index + 1, e))?,
+                    None => continue,
+                };
+
+                match next {
+                    Some(delta) => self.step(index, delta)?,
+                    None => streams[index] = None,
+                }
+            }
+        }
+
+        self.finish();
+        Ok(())
+    }
+
+    /// Follows a single stream of directions, the elevators taking its characters in turn.
+    ///
+    /// Fails if the stream cannot be read, or if an elevator goes beyond the building.
+    pub fn follow_round_robin<R: Read>(&mut self, input: R, alphabet: &Alphabet) -> Result<(), String> {
+        let mut bytes = io::BufReader::with_capacity(CHUNK_SIZE, input).bytes();
+        let mut offset = 0;
+        let mut turn = 0;
+
+        while let Some(delta) = next_move(&mut bytes, &mut offset, alphabet).map_err(|e| e.to_string())? {
+            self.step(turn % self.elevators.len(), delta)?;
+            turn += 1;
+        }
+
+        self.finish();
+        Ok(())
+    }
+
+    fn finish(&mut self) {
+        self.elevators = self.elevators.drain(..).map(Elevator::finish).collect();
+    }
+}
+
+/// Reads up to the next character that is not skipped and returns how it moves the elevator, see
+/// `Alphabet::read_byte`, or `None` at the end of the directions. `offset` counts the bytes read.
+fn next_move<R: Read>(bytes: &mut io::Bytes<R>, offset: &mut u64, alphabet: &Alphabet) -> io::Result<Option<Option<i64>>> {
+    for c in bytes {
+        let c = c?;
+        *offset += 1;
+        if !alphabet.skips(c) {
+            return alphabet.read_byte(c, *offset).map(Some);
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+const TIMELINE: Tracking = Tracking { timeline: true, brackets: false };
+
+#[test]
+fn single_elevator_test() {
+    // A single elevator with no floors out of reach follows the directions as before.
+    let cases = vec!("", "(())", "))(((((", "()())", "(()))))(()((((()))", "()))(");
+    for directions in cases {
+        let mut building = Building::new(1, None, None, Limit::Fail, TIMELINE).unwrap();
+        building.follow_round_robin(directions.as_bytes(), &Alphabet::default()).unwrap();
+        let elevator = ::elevator::follow_with(directions.as_bytes(), &Alphabet::default(), TIMELINE).unwrap();
+        assert_eq!(vec!(elevator), building.elevators, "{}", directions);
+
+        let mut each = Building::new(1, None, None, Limit::Fail, TIMELINE).unwrap();
+        each.follow_each(vec!(directions.as_bytes()), &Alphabet::default()).unwrap();
+        assert_eq!(building, each, "{}", directions);
+    }
+
+    let mut building = Building::new(1, None, None, Limit::Fail, TIMELINE).unwrap();
+    building.follow_round_robin("(()))))(()((((()))".as_bytes(), &Alphabet::default()).unwrap();
+    let events: Vec<(i64, u64)> = building.events.iter().map(|event| (event.floor, event.position)).collect();
+    assert_eq!(vec!((1, 1), (2, 2), (-1, 5), (-2, 6), (-3, 7), (3, 15)), events);
+}
+
+#[test]
+fn round_robin_test() {
+    let directions = "((((()))))))";
+
+    let mut building = Building::new(2, Some(-1), Some(1), Limit::Clamp, TIMELINE).unwrap();
+    building.follow_round_robin(directions.as_bytes(), &Alphabet::default()).unwrap();
+    let floors: Vec<i64> = building.elevators.iter().map(|elevator| elevator.floor).collect();
+    assert_eq!(vec!(-1, -1), floors);
+    assert_eq!(vec!(6, 6), building.elevators.iter().map(|elevator| elevator.position).collect::<Vec<u64>>());
+    assert_eq!(Some(5), building.elevators[0].basement);
+    assert_eq!(vec!(
+        Event { elevator: 0, floor: 1, position: 1 },
+        Event { elevator: 1, floor: 1, position: 1 },
+        Event { elevator: 1, floor: -1, position: 4 },
+        Event { elevator: 0, floor: -1, position: 5 },
+    ), building.events);
+
+    // Without a timeline, the elevators do the same but the events are not kept.
+    let mut building = Building::new(2, Some(-1), Some(1), Limit::Clamp, Tracking::default()).unwrap();
+    building.follow_round_robin(directions.as_bytes(), &Alphabet::default()).unwrap();
+    assert_eq!(vec!(-1, -1), building.elevators.iter().map(|elevator| elevator.floor).collect::<Vec<i64>>());
+    assert!(building.events.is_empty());
+
+    let mut building = Building::new(2, Some(-1), Some(1), Limit::Fail, TIMELINE).unwrap();
+    let error = building.follow_round_robin(directions.as_bytes(), &Alphabet::default());
+    assert_eq!(Err("elevator 1 would go to floor 2 at position 2".to_string()), error);
+
+    assert!(Building::new(0, None, None, Limit::Fail, TIMELINE).is_err());
+    assert!(Building::new(1, Some(1), None, Limit::Fail, TIMELINE).is_err());
+    assert!(Building::new(1, None, Some(-1), Limit::Fail, TIMELINE).is_err());
+}
+
+#[test]
+fn follow_each_test() {
+    let mut alphabet = Alphabet::default();
+    alphabet.ignore_whitespace = true;
+
+    let mut building = Building::new(3, None, Some(2), Limit::Clamp, TIMELINE).unwrap();
+    building.follow_each(vec!("(((((".as_bytes(), ") )".as_bytes(), "".as_bytes()), &alphabet).unwrap();
+    let floors: Vec<i64> = building.elevators.iter().map(|elevator| elevator.floor).collect();
+    assert_eq!(vec!(2, -2, 0), floors);
+    assert_eq!(vec!(
+        Event { elevator: 0, floor: 1, position: 1 },
+        Event { elevator: 1, floor: -1, position: 1 },
+        Event { elevator: 0, floor: 2, position: 2 },
+        Event { elevator: 1, floor: -2, position: 2 },
+    ), building.events);
+
+    alphabet.strict = true;
+    let mut building = Building::new(2, None, None, Limit::Clamp, TIMELINE).unwrap();
+    let error = building.follow_each(vec!("((".as_bytes(), "(x".as_bytes()), &alphabet);
+    assert_eq!(Err("elevator 2: 'x' at byte 2 is not part of the alphabet".to_string()), error);
+
+    let mut building = Building::new(2, None, None, Limit::Clamp, TIMELINE).unwrap();
+    assert!(building.follow_each(vec!("((".as_bytes()), &alphabet).is_err());
+}
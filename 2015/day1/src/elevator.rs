@@ -0,0 +1,463 @@
+//! Provides the elevator that follows Santa's directions, and everything it notices on the way.
+//!
+//! The directions are followed in a single pass. Besides the floor Santa ends up on, the elevator
+//! can keep a timeline of the trip: the highest and lowest floors and when they were reached, when
+//! each floor was first reached and how often it was visited, and the longest stretch spent below
+//! ground. The floors are kept in a map, so floors that are jumped over take no room. Without a
+//! timeline, the elevator follows the directions in constant memory.
+//!
+//! Which characters move the elevator, and by how much, is set by an Alphabet. Santa's directions
+//! use `(` to go up a floor and `)` to go down one. An alphabet may move by several floors at once,
+//! up to `MAX_DELTA`, in which case the elevator goes straight to the new floor without visiting
+//! the ones in between.
+//!
+//! The directions can also be matched up as brackets on the way, see `brackets`.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::io::prelude::*;
+use std::str::FromStr;
+
+use brackets::Brackets;
+
+/// The number of bytes read from the directions at a time.
+pub const CHUNK_SIZE: usize = 8192;
+
+/// The most floors a single character of an alphabet may move by, up or down.
+pub const MAX_DELTA: i64 = 1000000;
+
+/// Alphabet decides how each byte of the directions moves the elevator.
+#[derive(Clone)]
+pub struct Alphabet {
+    deltas: [Option<i64>; 256],
+    /// Whether whitespace is skipped over without counting as a position.
+    pub ignore_whitespace: bool,
+    /// Whether a byte outside of the alphabet stops the trip with an error, rather than being
+    /// reported and passed over.
+    pub strict: bool,
+}
+
+impl Alphabet {
+    /// Creates an alphabet from characters and the number of floors each one moves by.
+    ///
+    /// Fails if a character is not a single byte or appears twice, or if it moves by more than
+    /// `MAX_DELTA` floors.
+    pub fn new(deltas: &[(char, i64)]) -> Result<Alphabet, String> {
+        let mut alphabet = Alphabet { deltas: [None; 256], ignore_whitespace: false, strict: false };
+
+        for &(c, delta) in deltas {
+            if !c.is_ascii() {
+                return Err(format!("{:?} is not a single byte character", c));
+            }
+            if alphabet.deltas[c as usize].is_some() {
+                return Err(format!("{:?} appears more than once in the alphabet", c));
+            }
+            if delta.unsigned_abs() > MAX_DELTA as u64 {
+                return Err(format!("{:?} moves by {} floors, more than the {} allowed", c, delta, MAX_DELTA));
+            }
+            alphabet.deltas[c as usize] = Some(delta);
+        }
+
+        Ok(alphabet)
+    }
+
+    /// Returns the number of floors `c` moves by, if it is part of the alphabet.
+    pub fn delta(&self, c: u8) -> Option<i64> {
+        self.deltas[c as usize]
+    }
+
+    /// Returns whether `c` is skipped over without counting as a position.
+    pub fn skips(&self, c: u8) -> bool {
+        self.ignore_whitespace && c.is_ascii_whitespace()
+    }
+
+    /// Returns the number of floors that the byte `c` at `offset` in the input moves by, as
+    /// `Elevator::step` takes it. A byte outside of the alphabet is reported and moves nothing.
+    ///
+    /// In strict mode, a byte outside of the alphabet fails with an `InvalidData` error giving the
+    /// byte and its offset, counting from 1.
+    pub fn read_byte(&self, c: u8, offset: u64) -> io::Result<Option<i64>> {
+        let delta = self.delta(c);
+        if delta.is_none() {
+            if self.strict {
+                return Err(io::Error::new(io::ErrorKind::InvalidData,
+                    format!("{} at byte {} is not part of the alphabet", describe(c), offset)));
+            }
+            println!("Unknown character {}", describe(c));
+        }
+        Ok(delta)
+    }
+}
+
+impl Default for Alphabet {
+    /// Santa's alphabet, where `(` goes up one floor and `)` goes down one.
+    fn default() -> Alphabet {
+        Alphabet::new(&[('(', 1), (')', -1)]).unwrap()
+    }
+}
+
+impl fmt::Display for Alphabet {
+    /// Writes the alphabet in the form that `from_str` reads.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let pairs: Vec<String> = (0..256)
+            .filter_map(|c| self.deltas[c].map(|delta| format!("{}={}", c as u8 as char, delta)))
+            .collect();
+        write!(f, "{}", pairs.join(","))
+    }
+}
+
+impl FromStr for Alphabet {
+    type Err = String;
+
+    /// Reads an alphabet written as comma separated `character=delta` pairs, such as `(=1,)=-1`.
+    /// The character may itself be a comma or an equals sign.
+    fn from_str(s: &str) -> Result<Alphabet, String> {
+        let mut deltas = Vec::new();
+        let mut rest = s;
+
+        while !rest.is_empty() {
+            let mut chars = rest.chars();
+            let c = chars.next().unwrap();
+            let pair = chars.as_str().strip_prefix('=')
+                .ok_or(format!("alphabet {:?} should be pairs such as (=1,)=-1", s))?;
+            let (delta, next) = pair.split_once(',').unwrap_or((pair, ""));
+
+            let delta = i64::from_str(delta)
+                .map_err(|_| format!("{:?} is not a number of floors for {:?}", delta, c))?;
+            deltas.push((c, delta));
+            rest = next;
+        }
+
+        Alphabet::new(&deltas)
+    }
+}
+
+/// Extreme is the highest or lowest floor reached.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Extreme {
+    pub floor: i64,
+    /// The position at which the floor was first reached, where 0 is before the first character.
+    pub position: u64,
+}
+
+/// Visits is what happened on a single floor.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Visits {
+    /// The position at which the floor was first reached.
+    pub first: u64,
+    /// The number of times Santa arrived on the floor, counting the start for floor 0.
+    pub count: u64,
+}
+
+/// Stretch is a run of positions that Santa spent below ground.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Stretch {
+    /// The position at which Santa went below ground.
+    pub start: u64,
+    /// The number of positions spent below ground.
+    pub length: u64,
+}
+
+/// Tracking is what an elevator keeps track of besides its floor and when it enters the basement,
+/// which is all it needs constant memory for.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Tracking {
+    /// Whether to keep the timeline of the trip, see `Timeline`.
+    pub timeline: bool,
+    /// Whether to match up the directions as brackets, see `brackets`.
+    pub brackets: bool,
+}
+
+/// Timeline is the highest and lowest floors, the visits to each floor and the longest stretch
+/// below ground. It grows with the number of floors reached.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Timeline {
+    pub highest: Extreme,
+    pub lowest: Extreme,
+    pub longest_below: Option<Stretch>,
+    /// The visits to each floor reached.
+    visits: HashMap<i64, Visits>,
+    /// The position at which the current stretch below ground started.
+    below_since: Option<u64>,
+}
+
+impl Timeline {
+    fn new() -> Timeline {
+        Timeline {
+            highest: Extreme { floor: 0, position: 0 },
+            lowest: Extreme { floor: 0, position: 0 },
+            longest_below: None,
+            visits: vec!((0, Visits { first: 0, count: 1 })).into_iter().collect(),
+            below_since: None,
+        }
+    }
+
+    /// Records arriving on `floor` at `position`.
+    fn arrive(&mut self, floor: i64, position: u64) {
+        self.visits.entry(floor).or_insert(Visits { first: position, count: 0 }).count += 1;
+
+        if floor > self.highest.floor {
+            self.highest = Extreme { floor, position };
+        }
+        if floor < self.lowest.floor {
+            self.lowest = Extreme { floor, position };
+        }
+
+        match (floor < 0, self.below_since) {
+            (true, None) => self.below_since = Some(position),
+            (false, Some(start)) => {
+                self.end_stretch(start, position);
+                self.below_since = None;
+            },
+            _ => {},
+        }
+    }
+
+    /// Records a stretch below ground from `start` up to, but not including, `end`.
+    fn end_stretch(&mut self, start: u64, end: u64) {
+        let stretch = Stretch { start, length: end - start };
+        if self.longest_below.is_none_or(|longest| stretch.length > longest.length) {
+            self.longest_below = Some(stretch);
+        }
+    }
+
+    /// Returns what happened on `floor`, if it was ever reached.
+    pub fn visits(&self, floor: i64) -> Option<Visits> {
+        self.visits.get(&floor).cloned()
+    }
+
+    /// Returns every floor reached along with what happened on it, from the lowest floor up.
+    pub fn floors(&self) -> Vec<(i64, Visits)> {
+        let mut floors: Vec<(i64, Visits)> = self.visits.iter().map(|(&floor, &visits)| (floor, visits)).collect();
+        floors.sort_by_key(|&(floor, _)| floor);
+        floors
+    }
+}
+
+/// Elevator follows Santa's directions one character at a time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Elevator {
+    pub floor: i64,
+    /// The position of the last character followed, counting from 1.
+    pub position: u64,
+    /// The position of the character that first took Santa below ground.
+    pub basement: Option<u64>,
+    /// The timeline of the trip, if it is tracked.
+    pub timeline: Option<Timeline>,
+    /// The directions matched up as brackets, if they are tracked.
+    pub brackets: Option<Brackets>,
+}
+
+impl Elevator {
+    pub fn new(tracking: Tracking) -> Elevator {
+        Elevator {
+            floor: 0,
+            position: 0,
+            basement: None,
+            timeline: if tracking.timeline { Some(Timeline::new()) } else { None },
+            brackets: if tracking.brackets { Some(Brackets::default()) } else { None },
+        }
+    }
+
+    /// Follows a single character that moves the elevator by `delta` floors, or that leaves it
+    /// where it is if `delta` is `None`.
+    ///
+    /// Fails with an `InvalidData` error if the floor would overflow, leaving the elevator as it was.
+    pub fn step(&mut self, delta: Option<i64>) -> io::Result<()> {
+        let floor = match delta {
+            Some(delta) => Some(self.floor.checked_add(delta).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData,
+                format!("the floor goes out of range at position {}", self.position + 1)))?),
+            None => None,
+        };
+
+        self.position += 1;
+        if let Some(ref mut brackets) = self.brackets {
+            brackets.step(self.position, delta);
+        }
+
+        if let Some(floor) = floor {
+            self.floor = floor;
+            if let Some(ref mut timeline) = self.timeline {
+                timeline.arrive(floor, self.position);
+            }
+        }
+
+        if self.floor < 0 && self.basement.is_none() {
+            self.basement = Some(self.position);
+        }
+        Ok(())
+    }
+
+    /// Finishes the trip, closing any stretch below ground that lasted to the end.
+    pub fn finish(mut self) -> Elevator {
+        let end = self.position + 1;
+        if let Some(ref mut timeline) = self.timeline {
+            if let Some(start) = timeline.below_since.take() {
+                timeline.end_stretch(start, end);
+            }
+        }
+        self
+    }
+}
+
+/// Follows the directions read from `input` with `alphabet`, keeping track of what `tracking`
+/// asks for. The directions are read a chunk at a time, so they never need to fit in memory.
+pub fn follow_with<R: Read>(mut input: R, alphabet: &Alphabet, tracking: Tracking) -> io::Result<Elevator> {
+    let mut elevator = Elevator::new(tracking);
+    let mut buffer = [0; CHUNK_SIZE];
+    let mut offset = 0;
+
+    loop {
+        let count = match input.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(count) => count,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+
+        for &c in &buffer[..count] {
+            offset += 1;
+
+            if !alphabet.skips(c) {
+                elevator.step(alphabet.read_byte(c, offset)?)?;
+            }
+        }
+    }
+
+    Ok(elevator.finish())
+}
+
+/// Describes a byte for a message, as a character if it is ASCII.
+fn describe(c: u8) -> String {
+    if c.is_ascii() {
+        format!("{:?}", c as char)
+    } else {
+        format!("byte 0x{:02x}", c)
+    }
+}
+
+#[test]
+fn alphabet_test() {
+    let alphabet = Alphabet::from_str("U=2,,=-1,==0,)=-1").unwrap();
+    assert_eq!(Some(2), alphabet.delta(b'U'));
+    assert_eq!(Some(-1), alphabet.delta(b','));
+    assert_eq!(Some(0), alphabet.delta(b'='));
+    assert_eq!(None, alphabet.delta(b'('));
+    assert_eq!(")=-1,,=-1,==0,U=2", alphabet.to_string());
+    assert_eq!("(=1,)=-1", Alphabet::default().to_string());
+
+    let error = |s: &str| Alphabet::from_str(s).err();
+    assert_eq!(Some("'U' appears more than once in the alphabet".to_string()), error("U=1,U=2"));
+    assert_eq!(Some("\"up\" is not a number of floors for 'U'".to_string()), error("U=up"));
+    assert!(error("U").is_some());
+    assert_eq!(Some("'U' moves by 1000001 floors, more than the 1000000 allowed".to_string()), error("U=1000001"));
+    assert_eq!(Some("'d' moves by -1000001 floors, more than the 1000000 allowed".to_string()), error("d=-1000001"));
+    assert!(error("U=1000000,d=-1000000").is_none());
+    assert!(error("é=1").is_some());
+}
+
+#[test]
+fn follow_with_test() {
+    let mut alphabet = Alphabet::from_str("U=2,d=-1").unwrap();
+    let tracking = Tracking { timeline: true, brackets: false };
+    let elevator = follow_with("UdUdd\ndd\n".as_bytes(), &alphabet, tracking).unwrap();
+    assert_eq!((-1, 9, Some(8)), (elevator.floor, elevator.position, elevator.basement));
+    assert_eq!(vec!(
+        (-1, Visits { first: 8, count: 1 }),
+        (0, Visits { first: 0, count: 2 }),
+        (1, Visits { first: 2, count: 2 }),
+        (2, Visits { first: 1, count: 2 }),
+        (3, Visits { first: 3, count: 1 }),
+    ), elevator.timeline.unwrap().floors());
+
+    alphabet.ignore_whitespace = true;
+    let elevator = follow_with("UdUdd\ndd\n".as_bytes(), &alphabet, Tracking::default()).unwrap();
+    assert_eq!((-1, 7, Some(7)), (elevator.floor, elevator.position, elevator.basement));
+
+    alphabet.strict = true;
+    assert!(follow_with("UdUdd\ndd\n".as_bytes(), &alphabet, Tracking::default()).is_ok());
+    let error = follow_with("Ud \tUx".as_bytes(), &alphabet, Tracking::default()).unwrap_err();
+    assert_eq!(io::ErrorKind::InvalidData, error.kind());
+    assert_eq!("'x' at byte 6 is not part of the alphabet", error.to_string());
+
+    alphabet.ignore_whitespace = false;
+    let error = |input: &[u8]| follow_with(input, &alphabet, Tracking::default()).unwrap_err().to_string();
+    assert_eq!("' ' at byte 3 is not part of the alphabet", error(b"Ud \tUx"));
+    assert_eq!("byte 0xff at byte 2 is not part of the alphabet", error(b"U\xff"));
+
+    // A large jump only records the floor it lands on.
+    let alphabet = Alphabet::from_str("U=1000000,d=-1000000").unwrap();
+    let elevator = follow_with("UUdddd".as_bytes(), &alphabet, tracking).unwrap();
+    assert_eq!(-2000000, elevator.floor);
+    assert_eq!(vec!(-2000000, -1000000, 0, 1000000, 2000000),
+        elevator.timeline.unwrap().floors().iter().map(|&(floor, _)| floor).collect::<Vec<i64>>());
+
+    let mut elevator = Elevator::new(Tracking::default());
+    elevator.floor = i64::MAX - 1;
+    let error = elevator.step(Some(2)).unwrap_err();
+    assert_eq!(io::ErrorKind::InvalidData, error.kind());
+    assert_eq!("the floor goes out of range at position 1", error.to_string());
+    assert_eq!((i64::MAX - 1, 0), (elevator.floor, elevator.position));
+}
+
+#[test]
+fn follow_test() {
+    let cases: Vec<(&str, i64, Option<u64>)> = vec!(
+        ("(())", 0, None),
+        ("()()", 0, None),
+        ("(((", 3, None),
+        ("))(((((", 3, Some(1)),
+        ("())", -1, Some(3)),
+        (")())())", -3, Some(1)),
+        ("()())", -1, Some(5)),
+    );
+
+    for (directions, floor, basement) in cases {
+        let elevator = follow_with(directions.as_bytes(), &Alphabet::default(), Tracking::default()).unwrap();
+        assert_eq!((floor, basement), (elevator.floor, elevator.basement), "{}", directions);
+    }
+
+    // Directions longer than a chunk are followed across the chunk boundaries.
+    let directions = "(".repeat(CHUNK_SIZE * 2 + 10) + &")".repeat(CHUNK_SIZE * 2 + 11);
+    let elevator = follow_with(directions.as_bytes(), &Alphabet::default(), Tracking::default()).unwrap();
+    assert_eq!(-1, elevator.floor);
+    assert_eq!(Some(directions.len() as u64), elevator.basement);
+}
+
+#[test]
+fn timeline_test() {
+    let follow = |directions: &str| {
+        follow_with(directions.as_bytes(), &Alphabet::default(), Tracking { timeline: true, brackets: false }).unwrap()
+    };
+    let elevator = follow("(()))))(()((((()))");
+    let timeline = elevator.timeline.unwrap();
+
+    assert_eq!(0, elevator.floor);
+    assert_eq!(Extreme { floor: 3, position: 15 }, timeline.highest);
+    assert_eq!(Extreme { floor: -3, position: 7 }, timeline.lowest);
+    assert_eq!(Some(Stretch { start: 5, length: 7 }), timeline.longest_below);
+
+    assert_eq!(vec!(
+        (-3, Visits { first: 7, count: 1 }),
+        (-2, Visits { first: 6, count: 3 }),
+        (-1, Visits { first: 5, count: 3 }),
+        (0, Visits { first: 0, count: 4 }),
+        (1, Visits { first: 1, count: 4 }),
+        (2, Visits { first: 2, count: 3 }),
+        (3, Visits { first: 15, count: 1 }),
+    ), timeline.floors());
+    assert_eq!(None, timeline.visits(4));
+
+    // A stretch that lasts to the end of the directions still counts.
+    let timeline = follow("()))(").timeline.unwrap();
+    assert_eq!(Some(Stretch { start: 3, length: 3 }), timeline.longest_below);
+
+    let timeline = follow("").timeline.unwrap();
+    assert_eq!(vec!((0, Visits { first: 0, count: 1 })), timeline.floors());
+    assert_eq!(None, timeline.longest_below);
+
+    // Without a timeline only the floor and the basement are kept.
+    let elevator = follow_with("(()))))(()((((()))".as_bytes(), &Alphabet::default(), Tracking::default()).unwrap();
+    assert_eq!((0, Some(5), None), (elevator.floor, elevator.basement, elevator.timeline));
+}
@@ -1,26 +1,236 @@
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::str::FromStr;
+
+use building::{Building, Limit};
+use elevator::{Alphabet, Elevator, Timeline, Tracking};
+
+mod brackets;
+mod building;
+mod elevator;
+
+const USAGE: &str = "Usage: day1 [--report] [--brackets] [--target <floor>] [--alphabet <pairs>]
+            [--ignore-whitespace] [--strict] [--elevators <count>] [--lowest <floor>]
+            [--highest <floor>] [--clamp] [--events] [<file>|-]...
+
+Reads the directions from input.txt, from each <file>, or from standard input when given -.
+--report describes the whole trip as well as where it ends.
+--brackets matches up the directions as brackets, and --target also gives the fewest
+brackets to flip to end on <floor>.
+--alphabet sets the floors each character moves by, at most 1000000, as in the default (=1,)=-1.
+--ignore-whitespace skips whitespace without counting it as a position.
+--strict stops at the first character outside of the alphabet.
+
+With several files, each one is followed by an elevator of its own. --elevators instead has
+<count> elevators take the characters of a single file in turn.
+--lowest and --highest limit the floors of the building, and an elevator told to go beyond
+them fails unless --clamp has it wait at the last floor.
+--events lists when each elevator first reached each floor.";
+
+struct Options {
+    report: bool,
+    brackets: bool,
+    target: Option<i64>,
+    alphabet: Alphabet,
+    count: Option<usize>,
+    lowest: Option<i64>,
+    highest: Option<i64>,
+    limit: Limit,
+    events: bool,
+    paths: Vec<String>,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Options, String> {
+        let mut options = Options {
+            report: false,
+            brackets: false,
+            target: None,
+            alphabet: Alphabet::default(),
+            count: None,
+            lowest: None,
+            highest: None,
+            limit: Limit::Fail,
+            events: false,
+            paths: Vec::new(),
+        };
+        let mut ignore_whitespace = false;
+        let mut strict = false;
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+
+            match arg.as_str() {
+                "--report" => options.report = true,
+                "--brackets" => options.brackets = true,
+                "--target" => options.target = Some(parse_number(arg, value()?)?),
+                "--alphabet" => options.alphabet = Alphabet::from_str(value()?)?,
+                "--ignore-whitespace" => ignore_whitespace = true,
+                "--strict" => strict = true,
+                "--elevators" => options.count = Some(parse_number(arg, value()?)?),
+                "--lowest" => options.lowest = Some(parse_number(arg, value()?)?),
+                "--highest" => options.highest = Some(parse_number(arg, value()?)?),
+                "--clamp" => options.limit = Limit::Clamp,
+                "--events" => options.events = true,
+                _ if arg == "-" || !arg.starts_with('-') => options.paths.push(arg.clone()),
+                _ => return Err(format!("unknown option {:?}", arg)),
+            }
+        }
+
+        options.alphabet.ignore_whitespace = ignore_whitespace;
+        options.alphabet.strict = strict;
+        if options.paths.is_empty() {
+            options.paths.push("input.txt".to_string());
+        }
+        if options.count.is_some() && options.paths.len() > 1 {
+            return Err("--elevators takes a single file".to_string());
+        }
+
+        Ok(options)
+    }
+
+    /// Returns what the elevators need to keep track of for the options given, so that without
+    /// them the directions are followed in constant memory.
+    fn tracking(&self) -> Tracking {
+        Tracking { timeline: self.report || self.events, brackets: self.brackets || self.target.is_some() }
+    }
+
+    /// Returns whether the directions need a building rather than a single elevator on its own.
+    fn building(&self) -> bool {
+        self.paths.len() > 1 || self.count.is_some() || self.lowest.is_some() || self.highest.is_some() || self.events
+    }
+}
+
 fn main() {
-    let input = "(((())))()((((((((())()(()))(()((((()(()(((()((()((()(()()()()()))(((()(()((((((((((())(()()((())()(((())))()(()(()((()(()))(()()()()((()((()(((()()(((((((()()())()((((()()(((((()(())()(())((())()()))()(((((((())(()())(()(((())(()))((())))(()((()())))()())((((())))(()(((((()(())(((()()((()((()((((((((((())(()())))))()))())()()((((()()()()()()((((((())())(((()())()((()()(((()()()))(((((()))(((()(()()()(()(()(((())()))(()(((()((())()(()())())))((()()()(()()(((()))(((()((((()(((((()()(()())((()())())(()((((((()(()()))((((()))))())((())()()((()(()))))((((((((()))(()()(((())())(())()((()()()()((()((()((()()(((())))(()((())()((((((((()((()(()()(((())())())))(())())))()((((()))))))())))()()))()())((()())()((()()()))(()()(((()(())((((())())((((((((()()()()())))()()()((((()()))))))()((((()(((()))(()()())))((()()(((()))()()())())(((())((()()(())()()()(((())))))()())((()))()))((())()()())()())()()(()))())))())()))(())((()(())))(()(())(()))))(()(())())(()(())(()(()))))((()())()))()((((()()))))())))()()())((())()((()()()))()(((()(()))))(())()()))(((()())))))))))(((())))()))())()))))()()(((())))))))()(()()(()))((()))))((())))((()((())))())))()()(()))())()(()((()())(()(()()())())(()()))()))))(()())()()))()()()()))(()(()(()))))))()(()))()))()()(()((())(()(())))()(((())(())())))))()(()(()))))()))(()()()(())()(()(())))()))))()()(((((())))))())()())())())()())()))))()))))))))())()()()()()()())))()))((())()))())))()((())()))))()))())))))))())()()()))()()(()((((()(((((((()(())((()())((()()))()))))(())))()()()(())((())()())))(())))(())))(((()()))()(())(((()(()))((())))())()))((((()))())()))))))))()(())())))(()))()(()()))())()()(())())))())()()(()())))()((()())(()(())(())))))))))))))(()))))()))))))()()())(()(((((()(()())))())()))(()))()))(()()))()())(()))())()(())((()()))))))())))())()(((())))(()(()))()()))()(()))))))((()())(()))))))()())))()()))))))))((((((((()()()(()))))))()())))())))()()((())()))((())(())))())())))()()()((()((()(())))())()(())))))))))()())))()()()()()()))()))((())())(()(()))))))(()()))()))(())))()))))))))))))(()))))))))()))))()))()())()))()()))))))()))))((()))))(()))())()(())))(()())((((()())))()))))(()))()(()()(())))))())))))()))))))())))())))))())))())())))())(()))))(())()(())))())()))((()()))))))())))((())))))))())))(())))))()()())))))())))))()))))))()))()()()(()(((()())())())(()))())))))((()(())(()))))))))(())))()()()())())(()))))()()()))()))())())())()(())))()(((()((((())))))))()))))))))))))))))))))((())()())(()))))()()))))))(()()(())())))())))((())))((())))))))))))))()))))()(()))))))())))))()))(()()())(()())))))))))()))))))(())))))()()))()())(((())))()))(()))))))))(())())))())))())())())()()))((())()(())()())()))()())(())(()))))()())))(()(((()))))))()(()())()()()))()))))))))()()()(())()())()(((((()))()())())(()))))()()()(())))())))()((()())))(()))())()(()())())(()))()()))((()()))((()()()()())))(())()))(()(())))((()()))))))))())))))))())()()))))))))))))))))(())()(())(())()())())()))()(()))))())())))))()())()(()))()()(())))(())())))))(()))))))))))))))())())(())(())))(((()))()))))())((())(()))())))))))())))))())))()))()))))))))))))())()))))()))))((()))(())))()(())))(())()))()))())))())))))))()(()())())))()()())))(())))))(()))))))))))))(()))()))()))())))(((()()()(())((()())))()())(((()))(())()))((()()()())))())(())(()))))()(((((())))(()))())())))))))((((()()()))())())()(()(()())))))))))()())())))(())))()())(((()(())())()()))())())))))))((()())((()()(()))(()(())))()))()))(()))(()))()()(()(((())((((()))()(()))((())()(()(()())()(()))()())))))(()))()))())()())))())))(())))((())(()())))))()))(())(()))()())()(()()((()(()))))))()(())(()())(())()))(((())()))(()()(()()()))))(()(())))()))))())))))())(()()()()()()(((())))(()()))()((())(((((()()())))(()))(()))()()))(((())())()(((()()()()))))(()))(())())))()())(()()())())))))))()))))((())))()())(()))(()(()))())))))())(())))))()()())())()))()()(())))(()))(())((((((())(()))(()))())()))(()()(())))()))(()()))()))()(())))(())))((()(()))(())()()())())))(((()()())(())()))))))()(((()(((((()()(((())(())))())()((()))))((()())()(())(((())))(((()((()(()(()))(()()))())(()))(())(())))()))))))((((()))()((((()(()))()))()()))))()(()(()))()(()((()(((()(()()(((()))))()(((()(()(()(((()(()())())()()(()(()())())(()((((())(()))()))(((((()()())(())()((()()())))()()(((()()))()((((((((()(())))())((()))))(())))(()))))((()((((()()(())(((((()))(((((((((((((()())))((((()(((()((())())()))((()))()(()()((()()()()(()()(()(()(((())()(()((((((()((()()((())()((((()((()()(()()())((()()()((()((())()(()(((()((())((((())(()))((()(()))(()())()((((((((()(((((((((((()))(()(((()(()()()((((())((())()())()))(())((())(()))(((()((()(())))(()))))((()()))))((((()(()(()())(()(())((((((((()((((()((()(((((()))())()(()))(()()((()(())(((((()(())()(((((()()))))))()(((())()(()()((((())()((())((()(((())(((()))((()()((((()(())))))((()((((()((()((()(((())((()))(((((((()(((()((((((((())()))((((())(((((()((((((((()(((()((()(((()()(((()((((((()()(()((((((((()()(()(()(())((((()())()))))(((()))((((())((((()())((()(())()((()((((((()((((((()(())))()())(((())())())()(())()(()())((()()((((())((((((())(()(((((()((((())()((((()(()(())(()())(((())()((())((((()))()((((((())(()(((()(((()((((((()(((()))(()()())())((()((()())()((((())(((()(()(((((((((())(())))()((()()()()(())((()))(((((((()(((((((((()(()))))(()((((((((()((((()((()()((((((()()(((((((()(()(())()(())((()()()((()(((((()())()(((((()())()()((()(()())(()()()(((()()(((((()((((((()()((()(()()()((((((((((((()((((((((()()(((()())))()(((()()(())())((((()((((()((((()()()(())(())((()(()(((((((((((((((()(())(())))))()()))((()(((()(())((()(((()(()()((((()()(((()(((()(((((()()((()(()(((()))((((((()((((((((()((()((())(((((()(((())(())())((()()))((((())()()((()(((()(((((()()(((()))(((()(()(((((((((((((()))((((((((()(((()))))())((((((((((((())((())((()())(((())((())(()((((((((((()(((())((()()(()((())(((((((((((()))((((((((((((()(()())((()((()((()(()(((()((((((((()()(()((()(()(((()))((()))(((((((((((((()(())((((((())(((()(())(()(()(()((()()))((((()((((()((((())))())((((()((((()))((((((()((((((()((()(((())))((())(()))(()((()((((()((()(((()()))((((()()()(((((((())(((())(()))())((((()())(((()(((((((((((()(()(()((()(((((((((((((((()()((((()((((((((()(((()()((()((((()))(((()(())((((((()((((())()((((()((()))(())()(()(((()((())())((((((()(()(())())(((())(()(()())(((((()((()((())()())(())))(((()(())))))))(((()(((()))()((()(((()()((()())()()))())))(((()))(()(((()(((((((((()(()(((((()()(((()())()()))))()(((()))(((()(()(()(()(()))()(())()))(()(((())))(()))))))))))(())((()((())((()(())()(())((()()((((()()((()()))((())(((()((()(())(())))()(()(((((()((()))())()(((((()()(((()(()((((((())(()))(())()))((()(()()))(())())()))(((())))(()((()(((())(())())))((()()((((((((((((((()((()(()()(()(((()))())()()((()()()(())(()))(()())(((())((())()(())()()(()()(())))((()(((()))))(((()()(()()))())((()((())()))((((()()()())((())))(((()(())(((((()(((((()((()(()((((()()(((()()()(((()())(((()()((((())(()))(((()))(())())((()))(((()((()))(((()()((())((()(((((()((((()()())((()))()((((()((()(()()()(";
-
-    let mut floor = 0;
-    let mut pos = 1;
-    let mut pos_in_basement = 0;
-    let mut enter_basement = false;
-
-    for c in input.chars() {
-    	match c {
-    		'(' =>  floor = floor + 1,
-    		')' => floor = floor - 1,
-			_ => println!("Unknown character {}", c),
-    	}
-
-        if floor == -1 && !enter_basement {
-            pos_in_basement = pos;
-            enter_basement = true;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match Options::parse(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            println!("{}\n{}", e, USAGE);
+            return
+        },
+    };
+
+    if !options.building() {
+        let path = &options.paths[0];
+        match open(path).and_then(|input| elevator::follow_with(input, &options.alphabet, options.tracking())) {
+            Ok(elevator) => print_elevator(&elevator, &options),
+            Err(e) => println!("Cannot read {}: {}", path, e),
         }
+        return
+    }
+
+    match follow_building(&options) {
+        Ok(building) => {
+            for (i, elevator) in building.elevators.iter().enumerate() {
+                println!("Elevator {}:", i + 1);
+                print_elevator(elevator, &options);
+            }
 
-        pos = pos + 1;
+            if options.events {
+                println!("{:>8} {:>8} {:>10}", "Elevator", "Floor", "Position");
+                for event in &building.events {
+                    println!("{:>8} {:>8} {:>10}", event.elevator + 1, event.floor, event.position);
+                }
+            }
+        },
+        Err(e) => println!("Cannot follow the directions: {}", e),
     }
+}
+
+/// Parses the value of an option that is a number.
+fn parse_number<T: FromStr>(option: &str, value: &str) -> Result<T, String> {
+    T::from_str(value).map_err(|_| format!("{} must be a whole number, not {:?}", option, value))
+}
 
-    println!("Go to floor: {:?}", floor);
-    println!("Santa first enters the basement at position: {}", pos_in_basement);
+/// Follows the directions with the building described by `options`, see `building::Building`.
+fn follow_building(options: &Options) -> Result<Building, String> {
+    let count = options.count.unwrap_or(options.paths.len());
+    let mut building = Building::new(count, options.lowest, options.highest, options.limit, options.tracking())?;
+
+    let mut inputs = Vec::new();
+    for path in &options.paths {
+        inputs.push(open(path).map_err(|e| format!("cannot read {}: {}", path, e))?);
+    }
+
+    if options.count.is_some() {
+        building.follow_round_robin(inputs.remove(0), &options.alphabet)?;
+    } else {
+        building.follow_each(inputs, &options.alphabet)?;
+    }
+    Ok(building)
+}
+
+/// Opens the file at `path`, or standard input if `path` is -.
+fn open(path: &str) -> io::Result<Box<dyn Read>> {
+    if path == "-" {
+        Ok(Box::new(io::stdin().lock()))
+    } else {
+        Ok(Box::new(File::open(path)?))
+    }
+}
+
+/// Prints where an elevator ends up, along with the report and brackets when asked for.
+fn print_elevator(elevator: &Elevator, options: &Options) {
+    println!("Go to floor: {:?}", elevator.floor);
+    match elevator.basement {
+        Some(position) => println!("Santa first enters the basement at position: {}", position),
+        None => println!("Santa never enters the basement"),
+    }
+
+    if let Some(ref timeline) = elevator.timeline {
+        if options.report {
+            print_report(timeline);
+        }
+    }
+    if let Some(ref brackets) = elevator.brackets {
+        print_brackets(brackets, options.target);
+    }
+}
+
+/// Prints the timeline of the trip, see `elevator::Timeline`.
+fn print_report(timeline: &Timeline) {
+    println!("Highest floor: {} first reached at position {}", timeline.highest.floor, timeline.highest.position);
+    println!("Lowest floor: {} first reached at position {}", timeline.lowest.floor, timeline.lowest.position);
+    match timeline.longest_below {
+        Some(stretch) => println!("Longest stretch below ground: {} positions from position {}", stretch.length, stretch.start),
+        None => println!("Santa never goes below ground"),
+    }
+
+    println!("{:>8} {:>14} {:>8}", "Floor", "First reached", "Visits");
+    for (floor, visits) in timeline.floors() {
+        println!("{:>8} {:>14} {:>8}", floor, visits.first, visits.count);
+    }
+}
+
+/// Formats runs of consecutive positions as a list of positions and ranges, after a colon.
+fn format_runs(runs: &[(u64, u64)]) -> String {
+    let runs: Vec<String> = runs.iter().map(|&(first, length)| match length {
+        1 => first.to_string(),
+        _ => format!("{}-{}", first, first + length - 1),
+    }).collect();
+    if runs.is_empty() { String::new() } else { format!(" at {}", runs.join(", ")) }
+}
+
+/// Prints how the directions match up as brackets, see `brackets::Brackets`.
+fn print_brackets(brackets: &brackets::Brackets, target: Option<i64>) {
+    println!("Deepest nesting: {}", brackets.max_depth);
+    println!("Unmatched closing brackets: {}{}", brackets.unmatched, format_runs(&brackets.unmatched_runs));
+    println!("Brackets left open: {}{}", brackets.open, format_runs(&brackets.open_runs));
+    match brackets.longest_balanced {
+        Some(balanced) => println!("Longest balanced run: {} positions from position {}", balanced.length, balanced.start),
+        None => println!("No run of the directions is balanced"),
+    }
+    match brackets.flips_to_balance() {
+        Some(flips) => println!("Flips to balance: {}", flips),
+        None => println!("The brackets cannot be balanced"),
+    }
+    if let Some(floor) = target {
+        match brackets.flips_to_floor(floor) {
+            Some(flips) => println!("Flips to end on floor {}: {}", floor, flips),
+            None => println!("Floor {} cannot be reached by flipping brackets", floor),
+        }
+    }
 }
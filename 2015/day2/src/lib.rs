@@ -0,0 +1,106 @@
+//! Works out the wrapping paper and ribbon the elves need for a list of presents.
+//!
+//! `paper_required` and `ribbon_required` are the formulas of the puzzle for a box with sides in
+//! micrometres, as `parse_dimensions` reads them, built on `areas`, `smallest_perimeter` and
+//! `volume`. `present::Present` wraps boxes and cubes with them, and `report` reads a whole list
+//! of presents into a record of the wrapping for each one.
+
+pub mod present;
+pub mod prices;
+pub mod report;
+pub mod rolls;
+pub mod units;
+
+use units::{Unit, Units};
+
+/// Parses dimensions written as `LxWxH`, each of which is a number greater than zero with an
+/// optional unit, as lengths in micrometres. See `units::Units::parse_dimension`.
+pub fn parse_dimensions(dims: &str, units: Units) -> Result<(u64, u64, u64), String> {
+	let parsed = present::parse_list(dims, units, 3, "2x3x4")?;
+	Ok((parsed[0], parsed[1], parsed[2]))
+}
+
+/// Returns the areas in square micrometres of the three pairs of faces of a box with sides in
+/// micrometres.
+pub fn areas(l: u64, w: u64, h: u64) -> [u128; 3] {
+	let (l, w, h) = (l as u128, w as u128, h as u128);
+	[l * w, w * h, h * l]
+}
+
+/// Returns the smallest perimeter in micrometres of any face of a box with sides in micrometres.
+pub fn smallest_perimeter(l: u64, w: u64, h: u64) -> u128 {
+	let (l, w, h) = (l as u128, w as u128, h as u128);
+	2 * (l + w).min(w + h).min(h + l)
+}
+
+/// Returns the volume in cubic micrometres of a box with sides in micrometres, or `None` if it
+/// needs more than 128 bits.
+pub fn volume(l: u64, w: u64, h: u64) -> Option<u128> {
+	(l as u128 * w as u128).checked_mul(h as u128)
+}
+
+/// Returns the paper required in square micrometres for a box with sides in micrometres, or `None`
+/// if it needs more than 128 bits.
+pub fn paper_required(l: u64, w: u64, h: u64) -> Option<u128> {
+	let [area1, area2, area3] = areas(l, w, h);
+
+	let min_area = area1.min(area2).min(area3);
+
+	area1.checked_add(area2)?.checked_add(area3)?.checked_mul(2)?.checked_add(min_area)
+}
+
+/// Returns the ribbon required for a box with sides in micrometres, or `None` if it needs more
+/// than 128 bits. The bow is as long in `base` units as the volume is in cubic `base` units, so
+/// the ribbon is in cubic micrometres per square `base` unit, see `present::Present::ribbon`.
+pub fn ribbon_required(l: u64, w: u64, h: u64, base: Unit) -> Option<u128> {
+	let scale = base.micrometres() * base.micrometres();
+	smallest_perimeter(l, w, h).checked_mul(scale)?.checked_add(volume(l, w, h)?)
+}
+
+#[test]
+fn parse_dimensions_test() {
+	let ft = |feet: u64| feet * 304800;
+	assert_eq!(Ok((ft(2), ft(3), ft(4))), parse_dimensions("2x3x4", Units::default()));
+	assert_eq!(Ok((ft(1), ft(1), ft(10))), parse_dimensions("1x1x10\r", Units::default()));
+
+	assert!(parse_dimensions("2x3", Units::default()).is_err());
+	assert!(parse_dimensions("2x3x4x5", Units::default()).is_err());
+	assert!(parse_dimensions("2xx4", Units::default()).is_err());
+	assert_eq!(Err("the dimension \"0\" should be a number greater than zero, optionally followed by a unit such as 3in".to_string()), parse_dimensions("2x0x4", Units::default()));
+	assert_eq!(Err("the dimension \"-3\" should be a number greater than zero, optionally followed by a unit such as 3in".to_string()), parse_dimensions("2x-3x4", Units::default()));
+	assert!(parse_dimensions("18446744073709551616x1x1", Units::default()).is_err());
+
+	// Dimensions with units are exact whatever the base unit, while those without are in it.
+	let inches = Units { base: units::Unit::Inch, rounding: units::Rounding::Up };
+	assert_eq!(Ok((ft(2), 76200, 101600)), parse_dimensions("2ftx3inx4in", inches));
+	assert_eq!(Ok((100000, 200000, 50000)), parse_dimensions("10cmx20cmx5cm", inches));
+	let centimetres = Units { base: units::Unit::Centimetre, rounding: units::Rounding::Nearest };
+	assert_eq!(Ok((100000, ft(2), 25000)), parse_dimensions("100mmx2ftx2.5", centimetres));
+}
+
+#[test]
+fn required_test() {
+	let ft = |feet: u64| feet * 304800;
+	let (square, cube) = (304800u128 * 304800, 304800u128 * 304800 * 304800);
+	assert_eq!(Some(58 * square), paper_required(ft(2), ft(3), ft(4)));
+	assert_eq!(Some(43 * square), paper_required(ft(1), ft(1), ft(10)));
+	assert_eq!(Some(34 * cube), ribbon_required(ft(2), ft(3), ft(4), Unit::Foot));
+	assert_eq!(Some(14 * cube), ribbon_required(ft(1), ft(1), ft(10), Unit::Foot));
+
+	// The bow depends on the base unit, while the rest of the ribbon does not.
+	assert_eq!(Some(10 * 1000 * 1000 + 24), ribbon_required(2, 3, 4, Unit::Millimetre));
+
+	// Presents far too large for 64 bits are still worked out exactly.
+	assert_eq!(Some(7 << 64), paper_required(1 << 32, 1 << 32, 1 << 32));
+	assert_eq!(Some((1 << 96) + (4 << 32) * 92903040000), ribbon_required(1 << 32, 1 << 32, 1 << 32, Unit::Foot));
+
+	assert_eq!([6, 12, 8], areas(2, 3, 4));
+	assert_eq!(10, smallest_perimeter(2, 3, 4));
+	assert_eq!(Some(24), volume(2, 3, 4));
+	assert_eq!(4 << 64, smallest_perimeter(u64::MAX, u64::MAX, u64::MAX) + 4);
+	assert_eq!(None, volume(u64::MAX, u64::MAX, 2));
+
+	assert_eq!(None, paper_required(u64::MAX, u64::MAX, 1));
+	assert_eq!(None, ribbon_required(u64::MAX, u64::MAX, 2, Unit::Foot));
+	assert_eq!(None, ribbon_required(u64::MAX, u64::MAX, 1, Unit::Foot));
+}
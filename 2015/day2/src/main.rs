@@ -1,76 +1,294 @@
 #![feature(slice_patterns)]
 
+extern crate day2;
+
+use day2::{present, prices, report, rolls, units};
+use day2::units::Units;
+
+/// Options holds the settings given on the command line.
+///
+/// * `--report` prints the wrapping needed for each present, and statistics over all of them.
+/// * `--csv <file>` and `--json <file>` export the same, where a file of `-` is standard output.
+/// * `--unit <mm|cm|m|in|ft>` sets the base unit the wrapping is reported in, feet by default.
+///   Dimensions written without a unit are in the base unit, so this changes the size of those
+///   presents rather than converting them.
+/// * `--rounding <up|down|nearest>` sets how the wrapping of each present and the totals are
+///   rounded to whole base units, up by default. Dimensions are never rounded. See `units`.
+/// * `--rolls <W>x<L>` plans how the paper is cut from rolls of that width and length, in the same
+///   units as the presents. See `rolls`.
+/// * `--prices <file>` prints the cheapest packs to buy from a catalogue in CSV, or in TOML if the
+///   file ends in `.toml`, and what each present costs. See `prices`.
+struct Options {
+	report: bool,
+	csv: Option<String>,
+	json: Option<String>,
+	units: Units,
+	/// Whether the units were given on the command line, and so should be printed with the totals.
+	explicit_units: bool,
+	rolls: Option<rolls::Roll>,
+	prices: Option<String>,
+}
+
+const USAGE: &str = "Usage: day2 [--report] [--csv <file>|-] [--json <file>|-] [--unit <mm|cm|m|in|ft>] \
+	[--rounding <up|down|nearest>] [--rolls <W>x<L>] [--prices <file>]\n\
+	Dimensions without a unit, such as 2x3x4, are in the --unit, so changing it changes their size.";
+
+impl Options {
+	fn parse(args: &[String]) -> Result<Options, String> {
+		use std::str::FromStr;
+
+		let mut options = Options { report: false, csv: None, json: None, units: Units::default(), explicit_units: false, rolls: None, prices: None };
+		// The size of the rolls is read once the units are known.
+		let mut rolls = None;
+
+		let mut args = args.iter();
+		while let Some(arg) = args.next() {
+			let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+
+			match arg.as_str() {
+				"--report" => options.report = true,
+				"--csv" => options.csv = Some(value()?.clone()),
+				"--json" => options.json = Some(value()?.clone()),
+				"--unit" => {
+					options.units.base = units::Unit::from_str(value()?)?;
+					options.explicit_units = true;
+				},
+				"--rounding" => {
+					options.units.rounding = units::Rounding::from_str(value()?)?;
+					options.explicit_units = true;
+				},
+				"--rolls" => rolls = Some(value()?.clone()),
+				"--prices" => options.prices = Some(value()?.clone()),
+				_ => return Err(format!("unknown option {:?}", arg)),
+			}
+		}
+
+		if let Some(rolls) = rolls {
+			let sides = present::parse_list(&rolls, options.units, 2, "3x100")?;
+			options.rolls = Some(rolls::Roll { width: sides[0], length: sides[1] });
+		}
+
+		Ok(options)
+	}
+
+	/// Returns whether every record needs to be kept rather than only the totals.
+	fn needs_records(&self) -> bool {
+		self.report || self.csv.is_some() || self.json.is_some() || self.rolls.is_some() || self.prices.is_some()
+	}
+}
+
 fn main() {
 	use std::fs::File;
 	use std::io::prelude::*;
 
+	let args: Vec<String> = std::env::args().skip(1).collect();
+	let options = match Options::parse(&args) {
+		Ok(options) => options,
+		Err(e) => {
+			println!("{}", e);
+			println!("{}", USAGE);
+			return
+		}
+	};
+
 	let mut f = File::open("input.txt").unwrap();
 	let mut buffer = String::new();
 
 	f.read_to_string(&mut buffer).unwrap();
 
-	let mut total_paper = 0;
-	let mut total_ribbon = 0;
-	for input in buffer.lines() {
-		let dims = parse_dimensions(input);
-		match dims {
-			Some((l,w,h)) => {
-				total_paper = total_paper + paper_required(l, w, h);
-				total_ribbon = total_ribbon + ribbon_required(l, w, h);
-			},
-			None => {},
-		}
+	match totals(&buffer, options.units) {
+		Ok((total_paper, total_ribbon)) => {
+			println!("Total paper required: {}", total_paper);
+			println!("Total ribbon required: {}", total_ribbon);
+			if options.explicit_units {
+				let units = options.units;
+				println!("Units: paper in {}², ribbon in {}, rounded {} whole {}",
+					units.base, units.base, units.rounding, units.base);
+			}
+		},
+		Err(e) => {
+			println!("Cannot work out the wrapping: {}", e);
+			return
+		},
 	}
 
-	println!("Total paper required: {}", total_paper);
-	println!("Total ribbon required: {}", total_ribbon);
+	if options.needs_records() {
+		let records: Vec<report::Record> = report::records(&buffer, options.units).map(Result::unwrap).collect();
+		let units = options.units;
+		if options.report {
+			print_report(&records, units);
+		}
+		if let Some(ref path) = options.csv {
+			if let Err(e) = export(path, |out| report::write_csv(&records, units, out)) {
+				println!("Cannot write {}: {}", path, e);
+			}
+		}
+		if let Some(ref path) = options.json {
+			if let Err(e) = export(path, |out| report::write_json(&records, units, out)) {
+				println!("Cannot write {}: {}", path, e);
+			}
+		}
+		if let Some(roll) = options.rolls {
+			match plan_rolls(roll, &records, units) {
+				Ok(plan) => print_plan(&plan, units, options.report),
+				Err(e) => println!("Cannot plan the rolls: {}", e),
+			}
+		}
+		if let Some(ref path) = options.prices {
+			let bill = read_catalogue(path).and_then(|catalogue| print_bill(&catalogue, &records, units));
+			if let Err(e) = bill {
+				println!("Cannot work out the cost: {}", e);
+			}
+		}
+	}
 }
 
-fn parse_dimensions(dims: &str) -> Option<(i32, i32, i32)> {
-	use std::str::FromStr;
+/// Works out the total paper and ribbon required for the presents listed in `input`, see
+/// `report::records` and `report::Totals`.
+fn totals(input: &str, units: Units) -> Result<(u128, u128), String> {
+	let mut totals = report::Totals::default();
+	for record in report::records(input, units) {
+		totals.add(&record?)?;
+	}
 
-	let dims: Vec<&str> = dims.split('x').collect();
+	Ok(totals.rounded(units))
+}
 
-	if dims.len() != 3 {
-		None 
-	} else {
-		let length_result = i32::from_str(dims[0]);
-		let width_result = i32::from_str(dims[1]);
-		let height_result = i32::from_str(dims[2]);
+/// Prints a table of the wrapping needed for each present, followed by the summary.
+fn print_report(records: &[report::Record], units: Units) {
+	println!("{:>6} {:>20} {:>24} {:>6} {:>8} {:>9} {:>8} {:>8}",
+		"Line", "Present", "Face areas", "Slack", "Paper", "Perimeter", "Volume", "Ribbon");
+	for record in records {
+		let faces: Vec<String> = record.faces.iter().map(u64::to_string).collect();
+		println!("{:>6} {:>20} {:>24} {:>6} {:>8} {:>9} {:>8} {:>8}", record.line, record.present.format(units),
+			faces.join("/"), record.slack, record.paper, record.smallest_perimeter, record.volume, record.ribbon);
+	}
 
-		match (length_result, width_result, height_result) {
-			(Ok(l), Ok(w), Ok(h)) => Some((l, w, h)),
-			_ => None
-		}
+	let summary = match report::summarize(records, units) {
+		Ok(summary) => summary,
+		Err(e) => {
+			println!("Cannot summarize the presents: {}", e);
+			return
+		},
+	};
+	println!("Presents: {}", summary.count);
+	if let Some(largest) = summary.largest {
+		println!("Largest present: {} on line {}", largest.present.format(units), largest.line);
 	}
+	println!("Paper per present: mean {:.2}, median {}", summary.paper.mean, summary.paper.median);
+	println!("Ribbon per present: mean {:.2}, median {}", summary.ribbon.mean, summary.ribbon.median);
 }
 
-fn paper_required(l: i32, w: i32, h: i32) -> i32 {
-	let area1 = l * w;
-	let area2 = w * h;
-	let area3 = h * l;
+/// Plans how the sheets of paper for the presents are cut from rolls, see `rolls::plan`.
+fn plan_rolls(roll: rolls::Roll, records: &[report::Record], units: Units) -> Result<rolls::Plan, String> {
+	let mut sheets = Vec::new();
+	for record in records {
+		let (around, across) = record.present.sheet()
+			.ok_or(format!("line {}: the sheet for {} is too large", record.line, record.present.format(units)))?;
+		sheets.push((record.line, around, across));
+	}
+	rolls::plan(roll, &sheets).map_err(|line| {
+		let &(_, around, across) = sheets.iter().find(|&&(l, ..)| l == line).unwrap();
+		format!("the sheet of {}x{} for line {} does not fit on a roll of {}x{}", units.format(around),
+			units.format(across), line, units.format(roll.width), units.format(roll.length))
+	})
+}
 
-	let min_area = min(area1, min(area2, area3));
+/// Prints the number of rolls and the paper wasted in `units`, and with `cuts` where each sheet
+/// is cut.
+fn print_plan(plan: &rolls::Plan, units: Units, cuts: bool) {
+	if cuts {
+		for (i, roll) in plan.rolls.iter().enumerate() {
+			println!("Roll {}:", i + 1);
+			for cut in roll {
+				let turned = if cut.turned { ", turned" } else { "" };
+				println!("  line {}: {}x{} at {}, {}{}", cut.line, units.format(cut.width), units.format(cut.length),
+					units.format(cut.x), units.format(cut.y), turned);
+			}
+		}
+	}
 
-	(2 * area1) + (2 * area2) + (2 * area3) + min_area
+	let total = plan.rolls.len() as u128 * plan.roll.area();
+	let percent = if total == 0 { 0.0 } else { plan.waste() as f64 * 100.0 / total as f64 };
+	println!("Rolls needed: {}, at least {} by area", plan.rolls.len(), plan.lower_bound());
+	println!("Paper wasted: {} of {} ({:.2}%)", units.round(plan.waste(), 2), units.round(total, 2), percent);
 }
 
-fn ribbon_required(l: i32, w: i32, h: i32) -> i32 {
-	let perim1 = (2 * l) + (2 * w);
-	let perim2 = (2 * w) + (2 * h);
-	let perim3 = (2 * h) + (2 * l);
+/// Reads the catalogue at `path`, as TOML if it ends in `.toml` and as CSV otherwise.
+fn read_catalogue(path: &str) -> Result<prices::Catalogue, String> {
+	let input = std::fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+	let catalogue = if path.ends_with(".toml") {
+		prices::Catalogue::parse_toml(&input)
+	} else {
+		prices::Catalogue::parse_csv(&input)
+	};
+	catalogue.map_err(|e| format!("{}: {}", path, e))
+}
+
+/// Prints what each present costs, its share of the cheapest packs covering all the paper and
+/// ribbon, followed by the packs themselves and the total cost.
+fn print_bill(catalogue: &prices::Catalogue, records: &[report::Record], units: Units) -> Result<(), String> {
+	use prices::Kind;
 
-	let vol = l * w * h;
+	let mut totals = report::Totals::default();
+	for record in records {
+		totals.add(record)?;
+	}
+	let (total_paper, total_ribbon) = totals.rounded(units);
+	let paper = catalogue.cheapest(Kind::Paper, total_paper)?;
+	let ribbon = catalogue.cheapest(Kind::Ribbon, total_ribbon)?;
 
-	let min_perim = min(perim1, min(perim2, perim3));
+	// Each present pays for its share of the packs by its wrapping before it is rounded.
+	let exact_paper: Vec<u128> = records.iter().map(|record| record.exact_paper).collect();
+	let exact_ribbon: Vec<u128> = records.iter().map(|record| record.exact_ribbon).collect();
+	let paper_shares = prices::shares(paper.cost, &exact_paper)?;
+	let ribbon_shares = prices::shares(ribbon.cost, &exact_ribbon)?;
+	println!("{:>6} {:>20} {:>10} {:>10} {:>10}", "Line", "Present", "Paper", "Ribbon", "Cost");
+	for ((record, &paper_cost), &ribbon_cost) in records.iter().zip(&paper_shares).zip(&ribbon_shares) {
+		println!("{:>6} {:>20} {:>10} {:>10} {:>10}", record.line, record.present.format(units),
+			prices::price(paper_cost), prices::price(ribbon_cost), prices::price(paper_cost + ribbon_cost));
+	}
 
-	min_perim + vol
+	for &(name, needed, purchase) in &[("Paper", total_paper, &paper), ("Ribbon", total_ribbon, &ribbon)] {
+		println!("{}: {} needed, {} bought for {}", name, needed, purchase.size, prices::price(purchase.cost));
+		for &(i, count) in &purchase.packs {
+			let pack = &catalogue.packs[i];
+			println!("  {} x {} of {} at {}: {}", count, pack.name, pack.size, prices::price(pack.price as u128),
+				prices::price(count * pack.price as u128));
+		}
+	}
+	println!("Total cost: {}", prices::price(paper.cost + ribbon.cost));
+	Ok(())
 }
 
-fn min(first: i32, second: i32) -> i32 {
-	if first < second {
-		first
+/// Writes an export to the file at `path`, or to standard output if `path` is -.
+fn export<F>(path: &str, write: F) -> std::io::Result<()>
+	where F: FnOnce(&mut dyn std::io::Write) -> std::io::Result<()> {
+
+	if path == "-" {
+		let stdout = std::io::stdout();
+		let mut out = stdout.lock();
+		write(&mut out)
 	} else {
-		second
+		let mut out = std::io::BufWriter::new(std::fs::File::create(path)?);
+		write(&mut out)
 	}
 }
+
+#[test]
+fn totals_test() {
+	assert_eq!(Ok((58 + 43, 34 + 14)), totals("2x3x4\n\n1x1x10\n", Units::default()));
+	assert_eq!(Ok((0, 0)), totals("", Units::default()));
+	assert_eq!(Err("line 2: the dimension \"0\" should be a number greater than zero, optionally followed by a unit such as 3in".to_string()), totals("2x3x4\n0x1x1", Units::default()));
+	assert_eq!(Err("line 1: the present \"4294967296x4294967296x1\" is too large to wrap".to_string()), totals("4294967296x4294967296x1", Units::default()));
+
+	// Each total is rounded once, rather than for each present.
+	assert_eq!(Ok((1, 3)), totals("3inx3inx3in\n3inx3inx3in", Units::default()));
+	assert_eq!(Ok((2, 3)), totals("10cmx20cmx5cm\n3inx3inx3in", Units::default()));
+
+	// Totals keep growing past 64 bits.
+	let big = "2147483648x2147483648x1\n".repeat(3);
+	let paper = (1u128 << 63) + (1 << 33) + (1 << 31);
+	assert_eq!(Some(paper * 3), totals(&big, Units::default()).ok().map(|(paper, _)| paper));
+	assert!(paper * 3 > u64::MAX as u128);
+}
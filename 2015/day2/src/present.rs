@@ -0,0 +1,372 @@
+//! Provides the shapes of presents and the wrapping each one needs.
+//!
+//! A line of the list is a box written `LxWxH` as in the puzzle, or a shape marker followed by a
+//! space and the dimensions of that shape:
+//!
+//! * `box LxWxH` is the same as `LxWxH`.
+//! * `cube S` is a cube with sides of S, wrapped just as a box with three sides of S.
+//! * `cyl RxH` is a cylinder of radius R and height H.
+//! * `prism AxBxCxL` is a prism of length L whose ends are triangles with sides A, B and C.
+//!
+//! Every present needs its surface area of paper, plus the area of its smallest flat face as
+//! slack. It needs the smallest perimeter of any face of ribbon, plus its volume for the bow, where
+//! the perimeter of a cylinder is the shorter of the way around an end and the way around its
+//! length. Dimensions are kept in micrometres, see `units`. Boxes and cubes are worked out exactly,
+//! while the amounts of cylinders and prisms are rounded up to whole micrometres.
+
+use std::convert::TryFrom;
+
+use units::{Unit, Units};
+
+/// Present is a present of one of the shapes, with its dimensions in micrometres.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Present {
+	/// The length, width and height of a box.
+	Box(u64, u64, u64),
+	/// The side of a cube.
+	Cube(u64),
+	/// The radius and height of a cylinder.
+	Cylinder(u64, u64),
+	/// The three sides of the triangular ends and the length of a prism. The wrapping of a prism
+	/// whose sides do not make a triangle is `None`.
+	Prism(u64, u64, u64, u64),
+}
+
+impl Present {
+	/// Parses a present from a line of the list, with dimensions read in `units`.
+	pub fn parse(input: &str, units: Units) -> Result<Present, String> {
+		let input = input.trim();
+		let (marker, dims) = match input.split_once(' ') {
+			Some((marker, dims)) => (marker, dims.trim()),
+			None => ("box", input),
+		};
+
+		match marker {
+			"box" => {
+				let (l, w, h) = ::parse_dimensions(dims, units)?;
+				Ok(Present::Box(l, w, h))
+			},
+			"cube" => {
+				let dims = parse_list(dims, units, 1, "5")?;
+				Ok(Present::Cube(dims[0]))
+			},
+			"cyl" => {
+				let dims = parse_list(dims, units, 2, "3x4")?;
+				Ok(Present::Cylinder(dims[0], dims[1]))
+			},
+			"prism" => {
+				let dims = parse_list(dims, units, 4, "3x4x5x10")?;
+				if triangle_area(dims[0], dims[1], dims[2]).is_none() {
+					return Err(format!("the sides {}, {} and {} do not make a triangle",
+						units.format(dims[0]), units.format(dims[1]), units.format(dims[2])));
+				}
+				Ok(Present::Prism(dims[0], dims[1], dims[2], dims[3]))
+			},
+			_ => Err(format!("unknown shape {:?}, expected box, cube, cyl or prism", marker)),
+		}
+	}
+
+	/// Returns the name of the shape, as its marker is written.
+	pub fn shape(&self) -> &'static str {
+		match *self {
+			Present::Box(..) => "box",
+			Present::Cube(..) => "cube",
+			Present::Cylinder(..) => "cyl",
+			Present::Prism(..) => "prism",
+		}
+	}
+
+	/// Returns the dimensions of the present, in the order they are written.
+	pub fn dimensions(&self) -> Vec<u64> {
+		match *self {
+			Present::Box(l, w, h) => vec!(l, w, h),
+			Present::Cube(s) => vec!(s),
+			Present::Cylinder(r, h) => vec!(r, h),
+			Present::Prism(a, b, c, l) => vec!(a, b, c, l),
+		}
+	}
+
+	/// Returns the area of each different face in square micrometres: the three pairs of faces of
+	/// a box or cube, the ends and the curved side of a cylinder, or the ends and then each side of
+	/// a prism.
+	pub fn faces(&self) -> Option<Vec<u128>> {
+		match *self {
+			Present::Box(l, w, h) => Some(::areas(l, w, h).to_vec()),
+			Present::Cube(s) => Present::Box(s, s, s).faces(),
+			Present::Cylinder(r, h) => {
+				let (r, h) = (r as f64, h as f64);
+				Some(vec!(whole(PI * r * r)?, whole(2.0 * PI * r * h)?))
+			},
+			Present::Prism(a, b, c, l) => {
+				let end = triangle_area(a, b, c)?;
+				let l = l as f64;
+				Some(vec!(whole(end)?, whole(l * a as f64)?, whole(l * b as f64)?, whole(l * c as f64)?))
+			},
+		}
+	}
+
+	/// Returns the area of the smallest flat face, in square micrometres.
+	pub fn slack(&self) -> Option<u128> {
+		match *self {
+			Present::Cylinder(r, h) => whole(Measures::cylinder(r, h).slack),
+			Present::Box(..) | Present::Cube(..) | Present::Prism(..) => self.faces()?.into_iter().min(),
+		}
+	}
+
+	/// Returns the paper required in square micrometres, the surface area plus the slack.
+	pub fn paper(&self) -> Option<u128> {
+		match *self {
+			Present::Box(l, w, h) => ::paper_required(l, w, h),
+			Present::Cube(s) => Present::Box(s, s, s).paper(),
+			Present::Cylinder(r, h) => Measures::cylinder(r, h).paper(),
+			Present::Prism(a, b, c, l) => Measures::prism(a, b, c, l)?.paper(),
+		}
+	}
+
+	/// Returns the smallest perimeter of any face, in micrometres.
+	pub fn smallest_perimeter(&self) -> Option<u128> {
+		match *self {
+			Present::Box(l, w, h) => Some(::smallest_perimeter(l, w, h)),
+			Present::Cube(s) => Present::Box(s, s, s).smallest_perimeter(),
+			Present::Cylinder(r, h) => whole(Measures::cylinder(r, h).perimeter),
+			Present::Prism(a, b, c, l) => whole(Measures::prism(a, b, c, l)?.perimeter),
+		}
+	}
+
+	/// Returns the sides in micrometres of the rectangular sheet of paper the present is wrapped
+	/// in: the way around it, and its length plus the narrowest width of its ends so that half of
+	/// that can be folded over each end. A box is wrapped around its two shorter sides.
+	pub fn sheet(&self) -> Option<(u64, u64)> {
+		match *self {
+			Present::Box(l, w, h) => {
+				let mut sides = [l, w, h];
+				sides.sort();
+				let around = sides[0].checked_add(sides[1])?.checked_mul(2)?;
+				Some((around, sides[2].checked_add(sides[0])?))
+			},
+			Present::Cube(s) => Present::Box(s, s, s).sheet(),
+			Present::Cylinder(r, h) => {
+				let around = u64::try_from(whole(2.0 * PI * r as f64)?).ok()?;
+				Some((around, h.checked_add(r.checked_mul(2)?)?))
+			},
+			Present::Prism(a, b, c, l) => {
+				let around = a.checked_add(b)?.checked_add(c)?;
+				Some((around, l.checked_add(a.min(b).min(c))?))
+			},
+		}
+	}
+
+	/// Returns the volume in cubic micrometres.
+	pub fn volume(&self) -> Option<u128> {
+		match *self {
+			Present::Box(l, w, h) => ::volume(l, w, h),
+			Present::Cube(s) => Present::Box(s, s, s).volume(),
+			Present::Cylinder(r, h) => whole(Measures::cylinder(r, h).volume),
+			Present::Prism(a, b, c, l) => whole(Measures::prism(a, b, c, l)?.volume),
+		}
+	}
+
+	/// Returns the ribbon required, the smallest perimeter plus the volume for the bow.
+	///
+	/// The bow is as long in `base` units as the volume is in cubic `base` units, so the ribbon is
+	/// returned in cubic micrometres per square `base` unit, which `Units::round` converts as a
+	/// volume.
+	pub fn ribbon(&self, base: Unit) -> Option<u128> {
+		match *self {
+			Present::Box(l, w, h) => ::ribbon_required(l, w, h, base),
+			Present::Cube(s) => Present::Box(s, s, s).ribbon(base),
+			Present::Cylinder(..) | Present::Prism(..) => {
+				let scale = base.micrometres() * base.micrometres();
+				self.smallest_perimeter()?.checked_mul(scale)?.checked_add(self.volume()?)
+			},
+		}
+	}
+
+	/// Writes the present as it is written in the list with dimensions in `units`, leaving out the
+	/// marker of a box.
+	pub fn format(&self, units: Units) -> String {
+		let dimensions: Vec<String> = self.dimensions().into_iter().map(|d| units.format(d)).collect();
+		match *self {
+			Present::Box(..) => dimensions.join("x"),
+			_ => format!("{} {}", self.shape(), dimensions.join("x")),
+		}
+	}
+}
+
+const PI: f64 = ::std::f64::consts::PI;
+
+/// Measures holds the amounts of a cylinder or prism, which may not be whole numbers.
+struct Measures {
+	surface: f64,
+	slack: f64,
+	perimeter: f64,
+	volume: f64,
+}
+
+impl Measures {
+	fn cylinder(r: u64, h: u64) -> Measures {
+		let (r, h) = (r as f64, h as f64);
+		Measures {
+			surface: 2.0 * PI * r * r + 2.0 * PI * r * h,
+			slack: PI * r * r,
+			perimeter: (2.0 * PI * r).min(2.0 * (2.0 * r + h)),
+			volume: PI * r * r * h,
+		}
+	}
+
+	/// Returns the measures of a prism, or `None` if its sides do not make a triangle.
+	fn prism(a: u64, b: u64, c: u64, l: u64) -> Option<Measures> {
+		let end = triangle_area(a, b, c)?;
+		let (a, b, c, l) = (a as f64, b as f64, c as f64, l as f64);
+		let shortest = a.min(b).min(c);
+		Some(Measures {
+			surface: 2.0 * end + l * (a + b + c),
+			slack: end.min(l * shortest),
+			perimeter: (a + b + c).min(2.0 * (l + shortest)),
+			volume: end * l,
+		})
+	}
+
+	/// Returns the paper required, the surface area plus the slack, rounded up.
+	fn paper(&self) -> Option<u128> {
+		whole(self.surface + self.slack)
+	}
+}
+
+/// Rounds an amount up to a whole number, or returns `None` if it needs more than 128 bits.
+fn whole(amount: f64) -> Option<u128> {
+	let amount = amount.ceil();
+	if amount.is_finite() && amount < u128::MAX as f64 {
+		Some(amount as u128)
+	} else {
+		None
+	}
+}
+
+/// Returns the area of a triangle with sides a, b and c, or `None` if they do not make a triangle.
+///
+/// Heron's formula is worked out in integers as far as the square root, so that triangles with a
+/// whole or half area, such as 3, 4 and 5, are exact.
+fn triangle_area(a: u64, b: u64, c: u64) -> Option<f64> {
+	let (a, b, c) = (a as u128, b as u128, c as u128);
+	let factors = [a + b + c, (b + c).checked_sub(a)?, (c + a).checked_sub(b)?, (a + b).checked_sub(c)?];
+	if factors.contains(&0) {
+		return None;
+	}
+
+	// The product is sixteen times the square of the area.
+	match factors.iter().try_fold(1u128, |product, &factor| product.checked_mul(factor)) {
+		Some(product) => {
+			let root = product.isqrt();
+			if root * root == product {
+				Some(root as f64 / 4.0)
+			} else {
+				Some((product as f64).sqrt() / 4.0)
+			}
+		},
+		None => Some(factors.iter().map(|&factor| factor as f64).product::<f64>().sqrt() / 4.0),
+	}
+}
+
+/// Parses `count` dimensions separated by `x`, each with an optional unit, such as `example`, as
+/// lengths in micrometres.
+pub fn parse_list(dims: &str, units: Units, count: usize, example: &str) -> Result<Vec<u64>, String> {
+	let parts: Vec<&str> = dims.trim().split('x').collect();
+
+	if parts.len() != count {
+		let expected = match count {
+			1 => "one dimension".to_string(),
+			2 => "two dimensions".to_string(),
+			3 => "three dimensions".to_string(),
+			4 => "four dimensions".to_string(),
+			_ => format!("{} dimensions", count),
+		};
+		return Err(format!("{:?} should be {} such as {}", dims, expected, example));
+	}
+
+	parts.iter().map(|part| units.parse_dimension(part)).collect()
+}
+
+#[test]
+fn parse_test() {
+	let units = Units::default();
+	let ft = |feet: u64| feet * 304800;
+	assert_eq!(Ok(Present::Box(ft(2), ft(3), ft(4))), Present::parse("2x3x4", units));
+	assert_eq!(Ok(Present::Box(ft(2), ft(3), ft(4))), Present::parse("box 2x3x4", units));
+	assert_eq!(Ok(Present::Cube(ft(5))), Present::parse("cube 5", units));
+	assert_eq!(Ok(Present::Cylinder(ft(3), ft(4))), Present::parse(" cyl  3x4 ", units));
+	assert_eq!(Ok(Present::Prism(ft(3), ft(4), ft(5), ft(10))), Present::parse("prism 3x4x5x10", units));
+	assert_eq!(Ok(Present::Cylinder(ft(1), ft(2))), Present::parse("cyl 12inx2ft", units));
+	assert_eq!(Ok(Present::Box(100000, 200000, 50000)), Present::parse("10cmx20cmx5cm", units));
+
+	assert_eq!(Err("unknown shape \"sphere\", expected box, cube, cyl or prism".to_string()), Present::parse("sphere 3", units));
+	assert_eq!(Err("\"3x4\" should be one dimension such as 5".to_string()), Present::parse("cube 3x4", units));
+	assert_eq!(Err("\"3\" should be two dimensions such as 3x4".to_string()), Present::parse("cyl 3", units));
+	assert_eq!(Err("the sides 1, 2 and 3 do not make a triangle".to_string()), Present::parse("prism 1x2x3x4", units));
+	assert!(Present::parse("cyl 0x4", units).is_err());
+
+	for input in &["2x3x4", "cube 5", "cyl 3x4", "prism 3x4x5x10", "0.25x1.5x2"] {
+		assert_eq!(input.to_string(), Present::parse(input, units).unwrap().format(units));
+	}
+	assert_eq!("0.25x1.5x2", Present::parse("3inx18inx2", units).unwrap().format(units));
+}
+
+#[test]
+fn wrapping_test() {
+	// The wrapping in feet, rounded up.
+	let units = Units::default();
+	let ft = |feet: u64| feet * 304800;
+	let faces = |present: Present| {
+		present.faces().map(|faces| faces.into_iter().map(|face| units.round(face, 2)).collect::<Vec<u128>>())
+	};
+	let wrapping = |present: Present| (
+		present.slack().map(|slack| units.round(slack, 2)),
+		present.paper().map(|paper| units.round(paper, 2)),
+		present.smallest_perimeter().map(|perimeter| units.round(perimeter, 1)),
+		present.volume().map(|volume| units.round(volume, 3)),
+		present.ribbon(units.base).map(|ribbon| units.round(ribbon, 3)),
+	);
+
+	// Boxes and cubes are wrapped as in the puzzle.
+	let present = Present::Box(ft(2), ft(3), ft(4));
+	assert_eq!(Some(vec!(6, 12, 8)), faces(present));
+	assert_eq!((Some(6), Some(58), Some(10), Some(24), Some(34)), wrapping(present));
+	assert_eq!((Some(9), Some(63), Some(12), Some(27), Some(39)), wrapping(Present::Cube(ft(3))));
+	// A box of 3 by 3 by 3 inches needs 54 + 9 square inches of paper, not a square foot for each
+	// face.
+	let present = Present::Box(76200, 76200, 76200);
+	assert_eq!((Some(1), Some(1), Some(1), Some(1), Some(2)), wrapping(present));
+	assert_eq!(Some(63 * 25400 * 25400), present.paper());
+
+	// A cylinder of radius 1 and height 2 has 6π of surface, π of slack, a perimeter of 2π around an
+	// end and 2π of volume.
+	let present = Present::Cylinder(ft(1), ft(2));
+	assert_eq!(Some(vec!(4, 13)), faces(present));
+	assert_eq!((Some(4), Some(22), Some(7), Some(7), Some(13)), wrapping(present));
+	// A flat cylinder is shorter the way around its length.
+	assert_eq!(Some(ft(2 * (2 * 10 + 1)) as u128), Present::Cylinder(ft(10), ft(1)).smallest_perimeter());
+
+	// A prism with 3, 4, 5 ends has ends of 6, so with a length of 10 it has a surface of 12 + 120.
+	let present = Present::Prism(ft(3), ft(4), ft(5), ft(10));
+	assert_eq!(Some(vec!(6, 30, 40, 50)), faces(present));
+	assert_eq!((Some(6), Some(138), Some(12), Some(60), Some(72)), wrapping(present));
+	// An equilateral triangle with sides of 2 has an area of √3.
+	let present = Present::Prism(ft(2), ft(2), ft(2), ft(1));
+	assert_eq!(Some(vec!(2, 2, 2, 2)), faces(present));
+	assert_eq!((Some(2), Some(12), Some(6), Some(2), Some(8)), wrapping(present));
+
+	assert_eq!(Some((ft(10), ft(6))), Present::Box(ft(2), ft(3), ft(4)).sheet());
+	assert_eq!(Some((ft(10), ft(6))), Present::Box(ft(4), ft(2), ft(3)).sheet());
+	assert_eq!(Some((ft(12), ft(6))), Present::Cube(ft(3)).sheet());
+	assert_eq!(Some((1915115, ft(4))), Present::Cylinder(ft(1), ft(2)).sheet());
+	assert_eq!(Some((ft(12), ft(13))), Present::Prism(ft(3), ft(4), ft(5), ft(10)).sheet());
+
+	// A prism whose sides do not make a triangle cannot be wrapped.
+	for &present in &[Present::Prism(1, 2, 10, 1), Present::Prism(1, 2, 3, 4)] {
+		assert_eq!((None, None, None), (present.faces(), present.paper(), present.ribbon(units.base)));
+	}
+
+	assert_eq!(None, Present::Cylinder(u64::MAX, u64::MAX).paper());
+	assert_eq!(None, Present::Cube(u64::MAX).paper());
+	assert_eq!(None, Present::Cube(u64::MAX).volume());
+}
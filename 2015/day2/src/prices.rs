@@ -0,0 +1,422 @@
+//! Provides a catalogue of the packs of paper and ribbon for sale, and the cheapest way to buy
+//! enough of each.
+//!
+//! A pack holds a size of paper in square base units, or of ribbon in base units, for a price. A
+//! product sold by the unit is a pack with a size of 1. Prices are written with at most two
+//! decimals, such as `4.99`, and are kept as whole hundredths.
+//!
+//! The catalogue is written either as CSV, one pack per line:
+//!
+//! ```text
+//! kind,name,size,price
+//! paper,Small roll,50,2.49
+//! ribbon,Spool,100,1.99
+//! ```
+//!
+//! where the header is optional and names cannot contain commas, or as TOML, with a table for each
+//! pack:
+//!
+//! ```text
+//! [[paper]]
+//! name = "Small roll"
+//! size = 50
+//! price = 2.49
+//! ```
+//!
+//! Blank lines and lines starting with `#` are skipped in both.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Kind is what a pack holds.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Kind {
+	Paper,
+	Ribbon,
+}
+
+impl FromStr for Kind {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Kind, String> {
+		match s {
+			"paper" => Ok(Kind::Paper),
+			"ribbon" => Ok(Kind::Ribbon),
+			_ => Err(format!("unknown kind {:?}, expected paper or ribbon", s)),
+		}
+	}
+}
+
+impl fmt::Display for Kind {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			Kind::Paper => write!(f, "paper"),
+			Kind::Ribbon => write!(f, "ribbon"),
+		}
+	}
+}
+
+/// Pack is a pack for sale.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pack {
+	pub kind: Kind,
+	pub name: String,
+	/// The paper or ribbon in the pack.
+	pub size: u64,
+	/// The price in hundredths.
+	pub price: u64,
+}
+
+/// Catalogue is every pack for sale.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Catalogue {
+	pub packs: Vec<Pack>,
+}
+
+/// Purchase is a number of packs bought from a catalogue.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Purchase {
+	/// The index of each pack bought in the catalogue, and how many of it.
+	pub packs: Vec<(usize, u128)>,
+	/// The paper or ribbon bought.
+	pub size: u128,
+	/// The price in hundredths.
+	pub cost: u128,
+}
+
+/// The most sizes `Catalogue::cheapest` works through before giving up.
+const MAX_SIZES: u128 = 10000000;
+
+/// The cost of a size that `Catalogue::cheapest` cannot make from the packs.
+const UNMADE: u64 = u64::MAX;
+
+impl Catalogue {
+	/// Parses a catalogue written as CSV.
+	pub fn parse_csv(input: &str) -> Result<Catalogue, String> {
+		let mut packs = Vec::new();
+		// The header may only come before the first pack.
+		let mut first = true;
+		for (i, line) in input.lines().enumerate() {
+			let line = line.trim();
+			if line.is_empty() || line.starts_with('#') {
+				continue;
+			}
+			if std::mem::replace(&mut first, false) && line.starts_with("kind,") {
+				continue;
+			}
+
+			let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+			let pack = match fields[..] {
+				[kind, name, size, price] => pack(kind.parse(), name, size, price),
+				_ => Err(format!("expected kind,name,size,price but found {:?}", line)),
+			};
+			packs.push(pack.map_err(|e| format!("line {}: {}", i + 1, e))?);
+		}
+
+		Ok(Catalogue { packs })
+	}
+
+	/// Parses a catalogue written as TOML, with an array of tables for each kind.
+	pub fn parse_toml(input: &str) -> Result<Catalogue, String> {
+		/// The kind of a table and its keys, with the line it starts on.
+		type Table = (usize, Kind, Vec<(String, String)>);
+
+		let mut tables: Vec<Table> = Vec::new();
+		for (i, line) in input.lines().enumerate() {
+			let line = line.trim();
+			let error = |e: String| format!("line {}: {}", i + 1, e);
+			if line.is_empty() || line.starts_with('#') {
+				continue;
+			}
+
+			if let Some(kind) = line.strip_prefix("[[").and_then(|line| line.strip_suffix("]]")) {
+				tables.push((i + 1, kind.trim().parse().map_err(error)?, Vec::new()));
+				continue;
+			}
+
+			let (key, value) = line.split_once('=').ok_or_else(|| error(format!("expected key = value but found {:?}", line)))?;
+			let value = value.trim();
+			let value = match value.strip_prefix('"') {
+				Some(quoted) => {
+					let (string, rest) = quoted.split_once('"')
+						.ok_or_else(|| error(format!("the string {} is not closed", value)))?;
+					let rest = rest.trim();
+					if !rest.is_empty() && !rest.starts_with('#') {
+						return Err(error(format!("expected a comment after the string but found {:?}", rest)));
+					}
+					string
+				},
+				None => value.split('#').next().unwrap_or("").trim(),
+			};
+			match tables.last_mut() {
+				Some(&mut (_, _, ref mut keys)) => keys.push((key.trim().to_string(), value.to_string())),
+				None => return Err(error("keys should be inside a [[paper]] or [[ribbon]] table".to_string())),
+			}
+		}
+
+		let mut packs = Vec::new();
+		for (line, kind, keys) in tables {
+			let find = |name: &str| keys.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+				.ok_or(format!("line {}: the {} pack has no {}", line, kind, name));
+			if let Some((key, _)) = keys.iter().find(|(key, _)| !["name", "size", "price"].contains(&key.as_str())) {
+				return Err(format!("line {}: unknown key {:?}", line, key));
+			}
+			packs.push(pack(Ok(kind), find("name")?, find("size")?, find("price")?)
+				.map_err(|e| format!("line {}: {}", line, e))?);
+		}
+
+		Ok(Catalogue { packs })
+	}
+
+	/// Returns the cheapest packs of `kind` holding at least `amount` in all. Of purchases costing
+	/// the same, the one holding the least is chosen.
+	///
+	/// Fails if there are no packs of the kind, or if the packs are too large to compare.
+	pub fn cheapest(&self, kind: Kind, amount: u128) -> Result<Purchase, String> {
+		let packs: Vec<(usize, u128, u128)> = self.packs.iter().enumerate()
+			.filter(|&(_, pack)| pack.kind == kind)
+			.map(|(i, pack)| (i, pack.size as u128, pack.price as u128))
+			.collect();
+		if amount == 0 {
+			return Ok(Purchase { packs: Vec::new(), size: 0, cost: 0 });
+		}
+		if packs.is_empty() {
+			return Err(format!("the catalogue has no {}", kind));
+		}
+
+		// Sizes are counted in their greatest common divisor.
+		let unit = packs.iter().fold(0, |unit, &(_, size, _)| gcd(unit, size));
+		let amount = amount.div_ceil(unit);
+		let sizes: Vec<u128> = packs.iter().map(|&(_, size, _)| size / unit).collect();
+		let largest = *sizes.iter().max().unwrap();
+
+		// The pack with the lowest price for its size, the largest if several are as cheap.
+		let mut best = 0;
+		for p in 1..packs.len() {
+			let (price, best_price) = (packs[p].2 * sizes[best], packs[best].2 * sizes[p]);
+			if price < best_price || (price == best_price && sizes[p] > sizes[best]) {
+				best = p;
+			}
+		}
+
+		// Among any `sizes[best]` other packs, some hold a multiple of the best pack together, so
+		// they could be swapped for best packs costing no more. So only sizes up to that many of
+		// the largest pack need to be made exactly, and the rest is made up of best packs.
+		let limit = (sizes[best] * largest).min(amount + largest);
+		if limit > MAX_SIZES {
+			return Err(format!("the packs of {} are too large to compare", kind));
+		}
+
+		if packs.len() > u32::MAX as usize {
+			return Err(format!("there are too many packs of {} to compare", kind));
+		}
+
+		// The cheapest way to make each size exactly, or UNMADE, and the last pack of it. There may
+		// be millions of sizes, so the two are kept apart and as small as they can be.
+		let mut costs: Vec<u64> = vec!(UNMADE; limit as usize + 1);
+		let mut lasts: Vec<u32> = vec!(0; limit as usize + 1);
+		costs[0] = 0;
+		for total in 1..=limit as usize {
+			for (p, &size) in sizes.iter().enumerate() {
+				let rest = match total.checked_sub(size as usize) {
+					Some(rest) if costs[rest] != UNMADE => rest,
+					_ => continue,
+				};
+				let cost = costs[rest].checked_add(packs[p].2 as u64).filter(|&cost| cost != UNMADE)
+					.ok_or(format!("the packs of {} are too expensive to compare", kind))?;
+				if cost < costs[total] {
+					costs[total] = cost;
+					lasts[total] = p as u32;
+				}
+			}
+		}
+
+		// The exact size made up to the amount with best packs, as (cost, size, exact size, best packs).
+		let mut chosen: Option<(u128, u128, usize, u128)> = None;
+		for (total, &cost) in costs.iter().enumerate() {
+			if cost != UNMADE {
+				let count = amount.saturating_sub(total as u128).div_ceil(sizes[best]);
+				let (cost, size) = (cost as u128 + count * packs[best].2, total as u128 + count * sizes[best]);
+				if chosen.is_none_or(|(least, smallest, ..)| (cost, size) < (least, smallest)) {
+					chosen = Some((cost, size, total, count));
+				}
+			}
+		}
+
+		let (cost, size, mut total, count) = chosen.unwrap();
+		let mut counts = vec!(0; packs.len());
+		counts[best] += count;
+		while total > 0 {
+			let p = lasts[total] as usize;
+			counts[p] += 1;
+			total -= sizes[p] as usize;
+		}
+
+		Ok(Purchase {
+			packs: packs.iter().zip(counts).filter(|&(_, count)| count > 0).map(|(&(i, ..), count)| (i, count)).collect(),
+			size: size * unit,
+			cost,
+		})
+	}
+}
+
+/// Makes a pack from the fields read for it.
+fn pack(kind: Result<Kind, String>, name: &str, size: &str, price: &str) -> Result<Pack, String> {
+	let size = u64::from_str(size).ok().filter(|&size| size > 0)
+		.ok_or(format!("the size {:?} should be a whole number greater than zero", size))?;
+	Ok(Pack { kind: kind?, name: name.to_string(), size, price: parse_price(price)? })
+}
+
+/// Parses a price with at most two decimals as whole hundredths.
+pub fn parse_price(price: &str) -> Result<u64, String> {
+	let invalid = || format!("the price {:?} should be a number with at most two decimals", price);
+
+	let (whole, fraction) = price.split_once('.').unwrap_or((price, ""));
+	let digits = || whole.chars().chain(fraction.chars());
+	if digits().next().is_none() || !digits().all(|c| c.is_ascii_digit()) || fraction.len() > 2 {
+		return Err(invalid());
+	}
+	let whole = if whole.is_empty() { 0 } else { u64::from_str(whole).map_err(|_| invalid())? };
+	let fraction = format!("{:0<2}", fraction);
+	whole.checked_mul(100).and_then(|whole| whole.checked_add(u64::from_str(&fraction).unwrap())).ok_or_else(invalid)
+}
+
+/// Writes a price in hundredths with two decimals.
+pub fn price(hundredths: u128) -> String {
+	format!("{}.{:02}", hundredths / 100, hundredths % 100)
+}
+
+/// Shares out a cost in hundredths in proportion to `amounts`, in whole hundredths that add up to
+/// exactly `cost`. Each amount gets the whole hundredths of its share, and the hundredths left over
+/// go to the largest remainders. Amounts that are all zero share the cost equally.
+pub fn shares(cost: u128, amounts: &[u128]) -> Result<Vec<u128>, String> {
+	let too_large = || format!("the cost of {} is too large to share out", price(cost));
+
+	let mut total = amounts.iter().try_fold(0u128, |total, &amount| total.checked_add(amount)).ok_or_else(too_large)?;
+	let equal = total == 0;
+	if equal {
+		total = amounts.len() as u128;
+	}
+
+	let mut shares = Vec::with_capacity(amounts.len());
+	let mut remainders = Vec::with_capacity(amounts.len());
+	for (i, &amount) in amounts.iter().enumerate() {
+		let amount = if equal { 1 } else { amount };
+		let part = amount.checked_mul(cost).ok_or_else(too_large)?;
+		shares.push(part / total);
+		remainders.push((part % total, i));
+	}
+
+	let left = cost - shares.iter().sum::<u128>();
+	remainders.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+	for &(_, i) in remainders.iter().take(left as usize) {
+		shares[i] += 1;
+	}
+	Ok(shares)
+}
+
+fn gcd(a: u128, b: u128) -> u128 {
+	if b == 0 { a } else { gcd(b, a % b) }
+}
+
+#[test]
+fn parse_test() {
+	let csv = "kind,name,size,price\n\npaper,Small roll,50,2.49\n# Sold by the foot.\nribbon, Ribbon ,1,.1\n";
+	let catalogue = Catalogue::parse_csv(csv).unwrap();
+	assert_eq!(vec!(
+		Pack { kind: Kind::Paper, name: "Small roll".to_string(), size: 50, price: 249 },
+		Pack { kind: Kind::Ribbon, name: "Ribbon".to_string(), size: 1, price: 10 },
+	), catalogue.packs);
+
+	let toml = "# Sold by the foot.\n[[ribbon]]\nname = \"Ribbon\"\nsize = 1\nprice = 0.10 # a bargain\n\n\
+		[[paper]]\nprice = 2.49\nsize = 50\nname = \"Small roll\"\n";
+	let mut packs = catalogue.packs.clone();
+	packs.reverse();
+	assert_eq!(Ok(Catalogue { packs }), Catalogue::parse_toml(toml));
+
+	assert_eq!(Err("line 2: unknown kind \"tape\", expected paper or ribbon".to_string()), Catalogue::parse_csv("paper,Roll,50,2\ntape,Tape,1,1"));
+	assert_eq!(Err("line 1: the size \"0\" should be a whole number greater than zero".to_string()), Catalogue::parse_csv("paper,Roll,0,2"));
+	assert!(Catalogue::parse_csv("paper,Roll,50").is_err());
+	assert!(Catalogue::parse_csv("paper,Roll,50,2.499").is_err());
+	assert_eq!(Err("line 1: the ribbon pack has no price".to_string()), Catalogue::parse_toml("[[ribbon]]\nname = \"Spool\"\nsize = 1"));
+	assert_eq!(Err("line 2: unknown key \"colour\"".to_string()), Catalogue::parse_toml("\n[[paper]]\nname = \"A\"\nsize = 1\nprice = 1\ncolour = \"red\""));
+	assert!(Catalogue::parse_toml("name = \"Spool\"").is_err());
+	assert!(Catalogue::parse_toml("[[paper]]\nname = \"Spool").is_err());
+	assert_eq!(Ok("A".to_string()), Catalogue::parse_toml("[[paper]]\nname = \"A\" # x\nsize = 1\nprice = 1").map(|c| c.packs[0].name.clone()));
+	assert_eq!(Ok("A # x".to_string()), Catalogue::parse_toml("[[paper]]\nname = \"A # x\"\nsize = 1\nprice = 1").map(|c| c.packs[0].name.clone()));
+	assert_eq!(Err("line 2: expected a comment after the string but found \"B\\\"\"".to_string()), Catalogue::parse_toml("[[paper]]\nname = \"A\"B\""));
+
+	// The header may follow comments and blank lines, but not a pack.
+	assert_eq!(Ok(1), Catalogue::parse_csv("# Packs\n\nkind,name,size,price\npaper,Roll,50,2").map(|c| c.packs.len()));
+	assert_eq!(Err("line 2: the size \"size\" should be a whole number greater than zero".to_string()), Catalogue::parse_csv("paper,Roll,50,2\nkind,name,size,price"));
+
+	assert_eq!(Ok(499), parse_price("4.99"));
+	assert_eq!(Ok(400), parse_price("4"));
+	assert_eq!(Ok(450), parse_price("4.5"));
+	assert_eq!(Ok(5), parse_price(".05"));
+	for invalid in &["", ".", "-1", "1.234", "1,5", "1.2.3"] {
+		assert!(parse_price(invalid).is_err(), "{:?}", invalid);
+	}
+	assert_eq!("12.05", price(1205));
+	assert_eq!("0.00", price(0));
+}
+
+#[test]
+fn cheapest_test() {
+	let catalogue = Catalogue::parse_csv("paper,Sheet,10,3\npaper,Roll,25,6\nribbon,Spool,7,1").unwrap();
+
+	// Three sheets and a roll with a sheet both cost 9, but the sheets waste less.
+	assert_eq!(Ok(Purchase { packs: vec!((0, 3)), size: 30, cost: 900 }), catalogue.cheapest(Kind::Paper, 30));
+	assert_eq!(Ok(Purchase { packs: vec!((0, 2), (1, 1)), size: 45, cost: 1200 }), catalogue.cheapest(Kind::Paper, 41));
+	assert_eq!(Ok(Purchase { packs: vec!((0, 3), (1, 39998)), size: 1000000 - 20, cost: 23999700 }),
+		catalogue.cheapest(Kind::Paper, 1000000 - 20));
+	assert_eq!(Ok(Purchase { packs: vec!((2, 143)), size: 1001, cost: 14300 }), catalogue.cheapest(Kind::Ribbon, 1000));
+	assert_eq!(Ok(Purchase { packs: Vec::new(), size: 0, cost: 0 }), catalogue.cheapest(Kind::Ribbon, 0));
+
+	let paper = Catalogue::parse_csv("paper,Sheet,10,3").unwrap();
+	assert_eq!(Err("the catalogue has no ribbon".to_string()), paper.cheapest(Kind::Ribbon, 1));
+	let huge = Catalogue::parse_csv("paper,Sheet,100003,3\npaper,Roll,100019,3").unwrap();
+	assert_eq!(Err("the packs of paper are too large to compare".to_string()), huge.cheapest(Kind::Paper, 1 << 40));
+	let dear = Catalogue::parse_csv("paper,Sheet,1,1000000000000000\npaper,Roll,200,150000000000000000").unwrap();
+	assert_eq!(Err("the packs of paper are too expensive to compare".to_string()), dear.cheapest(Kind::Paper, 1000));
+
+	// Against every combination of a few packs.
+	let catalogue = Catalogue::parse_csv("paper,A,4,5\npaper,B,6,7\npaper,C,9,10\npaper,D,15,15").unwrap();
+	for amount in 1..100u128 {
+		let mut least = u128::MAX;
+		for a in 0..26 {
+			for b in 0..18 {
+				for c in 0..12 {
+					for d in 0..8 {
+						if 4 * a + 6 * b + 9 * c + 15 * d >= amount {
+							least = least.min(100 * (5 * a + 7 * b + 10 * c + 15 * d));
+						}
+					}
+				}
+			}
+		}
+		let purchase = catalogue.cheapest(Kind::Paper, amount).unwrap();
+		assert_eq!(least, purchase.cost, "{}", amount);
+		assert!(purchase.size >= amount);
+		let packs = &catalogue.packs;
+		assert_eq!(purchase.cost, purchase.packs.iter().map(|&(i, count)| count * packs[i].price as u128).sum::<u128>());
+		assert_eq!(purchase.size, purchase.packs.iter().map(|&(i, count)| count * packs[i].size as u128).sum::<u128>());
+	}
+}
+
+#[test]
+fn shares_test() {
+	assert_eq!(Ok(vec!(100, 200, 300, 400)), shares(1000, &[1, 2, 3, 4]));
+	assert_eq!(Ok(vec!(34, 33, 33)), shares(100, &[1, 1, 1]));
+	assert_eq!(Ok(vec!(1, 2, 0)), shares(3, &[5, 6, 0]));
+	assert_eq!(Ok(vec!(4, 3)), shares(7, &[0, 0]));
+	assert_eq!(Ok(Vec::new()), shares(0, &[]));
+	assert_eq!(Err("the cost of 1.00 is too large to share out".to_string()), shares(100, &[u128::MAX, 1]));
+	assert_eq!(Err("the cost of 1.00 is too large to share out".to_string()), shares(100, &[u128::MAX / 2, 1]));
+
+	// The shares always add up to the cost, however it divides.
+	for cost in 0..200 {
+		let amounts = [7, 13, 1, 0, 29, 3];
+		assert_eq!(cost, shares(cost, &amounts).unwrap().iter().sum::<u128>(), "{}", cost);
+	}
+}
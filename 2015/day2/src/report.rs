@@ -0,0 +1,365 @@
+//! Provides a breakdown of the wrapping needed for each present, and statistics over all of them.
+//!
+//! The records are read from the list of presents one line at a time, so the totals can be worked
+//! out without keeping every record. The statistics need every record, since the median does.
+//!
+//! The amounts of each record are in base units, rounded as the Units say, while totals add up the
+//! amounts before they are rounded and are rounded only once.
+
+use std::convert::TryFrom;
+use std::io;
+use std::io::prelude::*;
+use std::iter::Enumerate;
+use std::str::Lines;
+
+use present::Present;
+use units::Units;
+
+/// The fields of the CSV and JSON exports, in the order they are written.
+///
+/// The first twelve are those of a box, as they were exported before presents had shapes, and the
+/// sides and face areas among them are empty for any other shape. The last three describe a present
+/// of any shape.
+pub const FIELDS: [&str; 15] = [
+	"line", "length", "width", "height", "area1", "area2", "area3", "slack", "paper",
+	"smallest_perimeter", "volume", "ribbon", "shape", "dimensions", "faces",
+];
+
+/// Record is everything worked out about a single present, in base units.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+	/// The line of the list the present is on, counting from 1.
+	pub line: usize,
+	pub present: Present,
+	/// The area of each different face, see `Present::faces`.
+	pub faces: Vec<u64>,
+	/// The extra paper, the area of the smallest flat face.
+	pub slack: u64,
+	pub paper: u64,
+	pub smallest_perimeter: u64,
+	pub volume: u64,
+	pub ribbon: u64,
+	/// The paper and ribbon before they are rounded, see `Present::paper` and `Present::ribbon`.
+	pub exact_paper: u128,
+	pub exact_ribbon: u128,
+}
+
+impl Record {
+	/// Works out the record of a present in `units`, or returns `None` if any of it needs more
+	/// than 64 bits.
+	pub fn new(line: usize, present: Present, units: Units) -> Option<Record> {
+		let round = |amount: u128, power: u32| u64::try_from(units.round(amount, power)).ok();
+		let exact_paper = present.paper()?;
+		let exact_ribbon = present.ribbon(units.base)?;
+
+		Some(Record {
+			line,
+			present,
+			faces: present.faces()?.into_iter().map(|face| round(face, 2)).collect::<Option<Vec<u64>>>()?,
+			slack: round(present.slack()?, 2)?,
+			paper: round(exact_paper, 2)?,
+			smallest_perimeter: round(present.smallest_perimeter()?, 1)?,
+			volume: round(present.volume()?, 3)?,
+			ribbon: round(exact_ribbon, 3)?,
+			exact_paper,
+			exact_ribbon,
+		})
+	}
+
+	/// Returns the amounts worked out for the present, in the order of `FIELDS`.
+	pub fn amounts(&self) -> [u64; 5] {
+		[self.slack, self.paper, self.smallest_perimeter, self.volume, self.ribbon]
+	}
+
+	/// Returns the values of the first twelve of `FIELDS`, with the sides in `units`, or `None`
+	/// for the sides and face areas of a present that is not a box or cube.
+	pub fn values(&self, units: Units) -> Vec<Option<String>> {
+		let sides = match self.present {
+			Present::Box(l, w, h) => Some([l, w, h]),
+			Present::Cube(s) => Some([s, s, s]),
+			Present::Cylinder(..) | Present::Prism(..) => None,
+		};
+
+		let mut values = vec!(Some(self.line.to_string()));
+		values.extend((0..3).map(|i| sides.map(|sides| units.format(sides[i]))));
+		values.extend((0..3).map(|i| sides.map(|_| self.faces[i].to_string())));
+		values.extend(self.amounts().iter().map(|amount| Some(amount.to_string())));
+		values
+	}
+}
+
+/// Records reads a Record for each present in a list, one present per line. Blank lines are
+/// skipped.
+pub struct Records<'a> {
+	lines: Enumerate<Lines<'a>>,
+	units: Units,
+}
+
+impl<'a> Iterator for Records<'a> {
+	/// A line that is not a present, or whose present needs more than 64 bits, is an error.
+	type Item = Result<Record, String>;
+
+	fn next(&mut self) -> Option<Result<Record, String>> {
+		let (i, input) = self.lines.by_ref().find(|&(_, input)| !input.trim().is_empty())?;
+
+		Some(Present::parse(input, self.units)
+			.map_err(|e| format!("line {}: {}", i + 1, e))
+			.and_then(|present| Record::new(i + 1, present, self.units)
+				.ok_or(format!("line {}: the present {:?} is too large to wrap", i + 1, input))))
+	}
+}
+
+/// Returns the records of the presents listed in `input`, with dimensions read in `units`.
+pub fn records(input: &str, units: Units) -> Records<'_> {
+	Records { lines: input.lines().enumerate(), units }
+}
+
+/// Totals adds up the paper and ribbon of presents before they are rounded.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Totals {
+	paper: u128,
+	ribbon: u128,
+}
+
+impl Totals {
+	/// Adds the wrapping of a present, failing if a total needs more than 128 bits.
+	pub fn add(&mut self, record: &Record) -> Result<(), String> {
+		let too_large = || format!("line {}: the total wrapping is too large", record.line);
+		self.paper = self.paper.checked_add(record.exact_paper).ok_or_else(too_large)?;
+		self.ribbon = self.ribbon.checked_add(record.exact_ribbon).ok_or_else(too_large)?;
+		Ok(())
+	}
+
+	/// Returns the total paper and ribbon in base units, rounded as `units` says.
+	pub fn rounded(&self, units: Units) -> (u128, u128) {
+		(units.round(self.paper, 2), units.round(self.ribbon, 3))
+	}
+}
+
+/// Statistics describes one of the amounts worked out for each present.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Statistics {
+	/// The total, rounded once rather than for each present, see `Totals`.
+	pub total: u128,
+	pub mean: f64,
+	pub median: f64,
+}
+
+impl Statistics {
+	/// Works out the statistics of `values` with the given total, which are all 0 if there are no
+	/// values.
+	fn new(mut values: Vec<u64>, total: u128) -> Statistics {
+		if values.is_empty() {
+			return Statistics { total: 0, mean: 0.0, median: 0.0 };
+		}
+
+		values.sort();
+		let sum: u128 = values.iter().map(|&value| value as u128).sum();
+		let middle = values.len() / 2;
+		let median = if values.len().is_multiple_of(2) {
+			(values[middle - 1] as f64 + values[middle] as f64) / 2.0
+		} else {
+			values[middle] as f64
+		};
+
+		Statistics { total, mean: sum as f64 / values.len() as f64, median }
+	}
+}
+
+/// Summary describes a whole list of presents.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Summary {
+	pub count: usize,
+	/// The present with the largest volume, the first one if several are as large.
+	pub largest: Option<Record>,
+	pub paper: Statistics,
+	pub ribbon: Statistics,
+}
+
+/// Works out the summary of the presents in `records`, read in `units`.
+///
+/// Fails if a total needs more than 128 bits, see `Totals::add`.
+pub fn summarize(records: &[Record], units: Units) -> Result<Summary, String> {
+	let mut largest: Option<&Record> = None;
+	let mut totals = Totals::default();
+	for record in records {
+		if largest.is_none_or(|largest| record.present.volume() > largest.present.volume()) {
+			largest = Some(record);
+		}
+		totals.add(record)?;
+	}
+
+	let (paper, ribbon) = totals.rounded(units);
+	Ok(Summary {
+		count: records.len(),
+		largest: largest.cloned(),
+		paper: Statistics::new(records.iter().map(|record| record.paper).collect(), paper),
+		ribbon: Statistics::new(records.iter().map(|record| record.ribbon).collect(), ribbon),
+	})
+}
+
+/// Joins numbers with a separator.
+fn join(values: &[u64], separator: &str) -> String {
+	values.iter().map(u64::to_string).collect::<Vec<String>>().join(separator)
+}
+
+/// Writes the dimensions of a present in `units`, with a separator.
+fn dimensions(present: &Present, units: Units, separator: &str) -> String {
+	present.dimensions().into_iter().map(|d| units.format(d)).collect::<Vec<String>>().join(separator)
+}
+
+/// Writes the records as CSV, with a header line naming the fields, see `FIELDS`. The dimensions
+/// are written in the base unit as in the list, and the face areas are separated by slashes.
+pub fn write_csv<W: Write + ?Sized>(records: &[Record], units: Units, out: &mut W) -> io::Result<()> {
+	writeln!(out, "{}", FIELDS.join(","))?;
+	for record in records {
+		let values: Vec<String> = record.values(units).into_iter().map(Option::unwrap_or_default).collect();
+		writeln!(out, "{},{},{},{}", values.join(","), record.present.shape(), dimensions(&record.present, units, "x"),
+			join(&record.faces, "/"))?;
+	}
+	Ok(())
+}
+
+/// Writes the records and their summary as a JSON object, with the records as an array of objects
+/// under `presents` and the summary under `summary`. The fields of each record are named as in
+/// `FIELDS`, and are null where they are empty in CSV.
+pub fn write_json<W: Write + ?Sized>(records: &[Record], units: Units, out: &mut W) -> io::Result<()> {
+	let object = |record: &Record| {
+		let mut fields: Vec<String> = FIELDS.iter().zip(record.values(units))
+			.map(|(field, value)| format!("\"{}\": {}", field, value.unwrap_or_else(|| "null".to_string())))
+			.collect();
+		fields.push(format!("\"shape\": \"{}\"", record.present.shape()));
+		fields.push(format!("\"dimensions\": [{}]", dimensions(&record.present, units, ", ")));
+		fields.push(format!("\"faces\": [{}]", join(&record.faces, ", ")));
+		format!("{{{}}}", fields.join(", "))
+	};
+
+	fn statistics(statistics: &Statistics) -> String {
+		format!("{{\"total\": {}, \"mean\": {}, \"median\": {}}}", statistics.total, statistics.mean, statistics.median)
+	}
+
+	let summary = summarize(records, units).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+	writeln!(out, "{{")?;
+	writeln!(out, "  \"presents\": [")?;
+	for (i, record) in records.iter().enumerate() {
+		let separator = if i + 1 < records.len() { "," } else { "" };
+		writeln!(out, "    {}{}", object(record), separator)?;
+	}
+	writeln!(out, "  ],")?;
+	writeln!(out, "  \"summary\": {{")?;
+	writeln!(out, "    \"count\": {},", summary.count)?;
+	match summary.largest {
+		Some(ref largest) => writeln!(out, "    \"largest\": {},", object(largest))?,
+		None => writeln!(out, "    \"largest\": null,")?,
+	}
+	writeln!(out, "    \"paper\": {},", statistics(&summary.paper))?;
+	writeln!(out, "    \"ribbon\": {}", statistics(&summary.ribbon))?;
+	writeln!(out, "  }}")?;
+	writeln!(out, "}}")
+}
+
+#[test]
+fn records_test() {
+	let ft = |feet: u64| feet * 304800;
+	let found: Vec<Result<Record, String>> = records("2x3x4\n\n1x1x10\n0x1x1\n", Units::default()).collect();
+	assert_eq!(3, found.len());
+	assert_eq!(Ok(Record {
+		line: 1,
+		present: Present::Box(ft(2), ft(3), ft(4)),
+		faces: vec!(6, 12, 8),
+		slack: 6,
+		paper: 58,
+		smallest_perimeter: 10,
+		volume: 24,
+		ribbon: 34,
+		exact_paper: 58 * ft(1) as u128 * ft(1) as u128,
+		exact_ribbon: 34 * ft(1) as u128 * ft(1) as u128 * ft(1) as u128,
+	}), found[0]);
+	assert_eq!(Ok((3, 43, 4, 10, 14)), found[1].clone().map(|r| (r.line, r.paper, r.smallest_perimeter, r.volume, r.ribbon)));
+	assert_eq!(Err("line 4: the dimension \"0\" should be a number greater than zero, optionally followed by a unit such as 3in".to_string()), found[2]);
+
+	// Each amount is rounded, but from the exact size of the present.
+	let found = records("10cmx20cmx5cm\n3inx3inx3in", Units::default()).map(|r| r.map(|r| r.paper)).collect::<Vec<_>>();
+	assert_eq!(vec!(Ok(1), Ok(1)), found);
+
+	assert_eq!(None, Record::new(1, Present::Box(u64::MAX, u64::MAX, u64::MAX), Units::default()));
+	assert_eq!(0, records("\n \n", Units::default()).count());
+}
+
+#[test]
+fn totals_test() {
+	let units = Units::default();
+	let mut totals = Totals::default();
+	for record in records("3inx3inx3in\n".repeat(16).as_str(), units) {
+		totals.add(&record.unwrap()).unwrap();
+	}
+	// Sixteen presents each needing 63 square inches of paper need 7 square feet, not 16.
+	assert_eq!((7, 17), totals.rounded(units));
+
+	let inches = Units { base: ::units::Unit::Inch, rounding: ::units::Rounding::Nearest };
+	let mut totals = Totals::default();
+	for record in records("3x3x3\n3x3x3", inches) {
+		totals.add(&record.unwrap()).unwrap();
+	}
+	assert_eq!((2 * 63, 2 * 39), totals.rounded(inches));
+
+	let mut totals = Totals::default();
+	let record = Record { exact_paper: u128::MAX, ..records("3x3x3", inches).next().unwrap().unwrap() };
+	assert_eq!(Ok(()), totals.add(&record));
+	assert_eq!(Err("line 1: the total wrapping is too large".to_string()), totals.add(&record));
+}
+
+#[test]
+fn summarize_test() {
+	let found: Vec<Record> = records("2x3x4\n1x1x10\n3x3x3\n1x1x1", Units::default()).map(Result::unwrap).collect();
+	let summary = summarize(&found, Units::default()).unwrap();
+
+	assert_eq!(4, summary.count);
+	assert_eq!(Some(3), summary.largest.map(|largest| largest.line));
+	assert_eq!(Statistics { total: 58 + 43 + 63 + 7, mean: 171.0 / 4.0, median: 50.5 }, summary.paper);
+	assert_eq!(Statistics { total: 34 + 14 + 39 + 5, mean: 23.0, median: 24.0 }, summary.ribbon);
+
+	// The totals are rounded once, rather than for each present.
+	let found: Vec<Record> = records("3inx3inx3in\n3inx3inx3in", Units::default()).map(Result::unwrap).collect();
+	let summary = summarize(&found, Units::default()).unwrap();
+	assert_eq!(Statistics { total: 1, mean: 1.0, median: 1.0 }, summary.paper);
+
+	assert_eq!(None, summarize(&[], Units::default()).unwrap().largest);
+	assert_eq!(Statistics { total: 0, mean: 0.0, median: 0.0 }, summarize(&[], Units::default()).unwrap().paper);
+}
+
+#[test]
+fn export_test() {
+	let found: Vec<Record> = records("2x3x4\ncyl 1x2\ncube 0.5", Units::default()).map(Result::unwrap).collect();
+
+	// Boxes are exported with the fields they had before presents had shapes.
+	let mut csv = Vec::new();
+	write_csv(&found, Units::default(), &mut csv).unwrap();
+	assert_eq!("line,length,width,height,area1,area2,area3,slack,paper,smallest_perimeter,volume,ribbon,shape,dimensions,faces\n\
+		1,2,3,4,6,12,8,6,58,10,24,34,box,2x3x4,6/12/8\n\
+		2,,,,,,,4,22,7,7,13,cyl,1x2,4/13\n\
+		3,0.5,0.5,0.5,1,1,1,1,2,2,1,3,cube,0.5,1/1/1\n", String::from_utf8(csv).unwrap());
+
+	let mut json = Vec::new();
+	write_json(&found[..1], Units::default(), &mut json).unwrap();
+	let expected = "{\n  \"presents\": [\n    {\"line\": 1, \"length\": 2, \"width\": 3, \"height\": 4, \
+		\"area1\": 6, \"area2\": 12, \"area3\": 8, \"slack\": 6, \"paper\": 58, \"smallest_perimeter\": 10, \
+		\"volume\": 24, \"ribbon\": 34, \"shape\": \"box\", \"dimensions\": [2, 3, 4], \"faces\": [6, 12, 8]}\n  ],\n  \
+		\"summary\": {\n    \"count\": 1,\n    \"largest\": {\"line\": 1, \
+		\"length\": 2, \"width\": 3, \"height\": 4, \"area1\": 6, \"area2\": 12, \"area3\": 8, \"slack\": 6, \
+		\"paper\": 58, \"smallest_perimeter\": 10, \"volume\": 24, \"ribbon\": 34, \"shape\": \"box\", \
+		\"dimensions\": [2, 3, 4], \"faces\": [6, 12, 8]},\n    \
+		\"paper\": {\"total\": 58, \"mean\": 58, \"median\": 58},\n    \
+		\"ribbon\": {\"total\": 34, \"mean\": 34, \"median\": 34}\n  }\n}\n";
+	assert_eq!(expected, String::from_utf8(json).unwrap());
+
+	let mut json = Vec::new();
+	write_json(&found[1..2], Units::default(), &mut json).unwrap();
+	assert!(String::from_utf8(json).unwrap().contains("{\"line\": 2, \"length\": null, \"width\": null, \"height\": null, \
+		\"area1\": null, \"area2\": null, \"area3\": null, \"slack\": 4,"));
+
+	let mut json = Vec::new();
+	write_json(&[], Units::default(), &mut json).unwrap();
+	assert!(String::from_utf8(json).unwrap().contains("\"largest\": null"));
+}
@@ -0,0 +1,202 @@
+//! Plans how the sheets of paper for the presents are cut from rolls of a fixed size.
+//!
+//! Each present is wrapped in a single rectangular sheet, see `Present::sheet`, and every sheet is
+//! cut from a roll with straight cuts right across the paper left over, as a guillotine does. The
+//! sheets are placed largest first, each into the smallest space left on any roll that it fits,
+//! turned if it has to be, and a new roll is started only when it fits nowhere. This is a
+//! heuristic, so it may need more rolls than the best plan, but never fewer than the lower bound of
+//! the area of all sheets over the area of a roll.
+//!
+//! Sides are in micrometres, as the presents are, see `units`.
+
+/// Roll is the size of a roll of paper.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Roll {
+	/// The width across the roll.
+	pub width: u64,
+	/// The length of paper on the roll.
+	pub length: u64,
+}
+
+impl Roll {
+	/// Returns the area of paper on the roll.
+	pub fn area(&self) -> u128 {
+		self.width as u128 * self.length as u128
+	}
+}
+
+/// Cut is a sheet cut from a roll, as it lies on the roll.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Cut {
+	/// The line of the list of the present the sheet is for.
+	pub line: usize,
+	/// The distance of the sheet from the edge of the roll.
+	pub x: u64,
+	/// The distance of the sheet from the start of the roll.
+	pub y: u64,
+	pub width: u64,
+	pub length: u64,
+	/// Whether the sheet was turned to fit.
+	pub turned: bool,
+}
+
+/// Plan is the sheets cut from each roll.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Plan {
+	pub roll: Roll,
+	/// The cuts of each roll, in the order they were placed.
+	pub rolls: Vec<Vec<Cut>>,
+	/// The area of all sheets.
+	pub area: u128,
+}
+
+impl Plan {
+	/// Returns the number of rolls the sheets would need if none of the paper were wasted.
+	pub fn lower_bound(&self) -> u128 {
+		self.area.div_ceil(self.roll.area())
+	}
+
+	/// Returns the area of paper on the rolls that is not cut into sheets.
+	pub fn waste(&self) -> u128 {
+		self.rolls.len() as u128 * self.roll.area() - self.area
+	}
+}
+
+/// Space is a rectangle of a roll that is not cut yet.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Space {
+	x: u64,
+	y: u64,
+	width: u64,
+	length: u64,
+}
+
+impl Space {
+	fn area(&self) -> u128 {
+		self.width as u128 * self.length as u128
+	}
+}
+
+/// Plans the cuts of sheets given as the line of their present and their sides.
+///
+/// Fails with the line of the first sheet, largest first, that is larger than a roll whichever way
+/// it is turned.
+pub fn plan(roll: Roll, sheets: &[(usize, u64, u64)]) -> Result<Plan, usize> {
+	let mut sheets = sheets.to_vec();
+	sheets.sort_by_key(|&(_, a, b)| ::std::cmp::Reverse(a as u128 * b as u128));
+
+	let mut plan = Plan { roll, rolls: Vec::new(), area: 0 };
+	// The spaces left on each roll.
+	let mut spaces: Vec<Vec<Space>> = Vec::new();
+
+	for (line, a, b) in sheets {
+		let orientations = if a == b { vec!((a, b, false)) } else { vec!((a, b, false), (b, a, true)) };
+
+		// The roll, space and orientation leaving the least area of the space unused.
+		let mut best: Option<(u128, usize, usize, usize)> = None;
+		for (r, roll_spaces) in spaces.iter().enumerate() {
+			for (s, space) in roll_spaces.iter().enumerate() {
+				for (o, &(width, length, _)) in orientations.iter().enumerate() {
+					if width > space.width || length > space.length {
+						continue;
+					}
+					let unused = space.area() - width as u128 * length as u128;
+					if best.is_none_or(|(least, ..)| unused < least) {
+						best = Some((unused, r, s, o));
+					}
+				}
+			}
+		}
+
+		let (r, s, (width, length, turned)) = match best {
+			Some((_, r, s, o)) => (r, s, orientations[o]),
+			None => {
+				let &orientation = orientations.iter()
+					.find(|&&(width, length, _)| width <= roll.width && length <= roll.length)
+					.ok_or(line)?;
+				spaces.push(vec!(Space { x: 0, y: 0, width: roll.width, length: roll.length }));
+				plan.rolls.push(Vec::new());
+				(spaces.len() - 1, 0, orientation)
+			},
+		};
+
+		let space = spaces[r].swap_remove(s);
+		plan.rolls[r].push(Cut { line, x: space.x, y: space.y, width, length, turned });
+		plan.area += width as u128 * length as u128;
+
+		// The paper beside and after the sheet is split so that the larger piece is kept whole.
+		let (beside, after) = (space.width - width, space.length - length);
+		let (beside, after) = if beside < after {
+			(Space { x: space.x + width, y: space.y, width: beside, length },
+				Space { x: space.x, y: space.y + length, width: space.width, length: after })
+		} else {
+			(Space { x: space.x + width, y: space.y, width: beside, length: space.length },
+				Space { x: space.x, y: space.y + length, width, length: after })
+		};
+		spaces[r].extend([beside, after].iter().filter(|space| space.area() > 0));
+	}
+
+	Ok(plan)
+}
+
+#[test]
+fn plan_test() {
+	let roll = Roll { width: 10, length: 10 };
+
+	let found = plan(roll, &[(1, 5, 5), (2, 5, 5), (3, 5, 5), (4, 5, 5)]).unwrap();
+	assert_eq!(1, found.rolls.len());
+	assert_eq!((1, 0), (found.lower_bound(), found.waste()));
+
+	// Squares of 6 cannot share a roll of 10, though two would by area.
+	let found = plan(roll, &[(1, 6, 6), (2, 6, 6), (3, 6, 6)]).unwrap();
+	assert_eq!(3, found.rolls.len());
+	assert_eq!((2, 300 - 108), (found.lower_bound(), found.waste()));
+
+	// The long sheet is placed first, and the second fits in the paper after it.
+	let found = plan(roll, &[(1, 6, 4), (2, 10, 6)]).unwrap();
+	assert_eq!(vec!(vec!(
+		Cut { line: 2, x: 0, y: 0, width: 10, length: 6, turned: false },
+		Cut { line: 1, x: 0, y: 6, width: 6, length: 4, turned: false },
+	)), found.rolls);
+	// A sheet wider than the roll is turned.
+	let found = plan(Roll { width: 4, length: 20 }, &[(1, 10, 4)]).unwrap();
+	assert_eq!(vec!(vec!(Cut { line: 1, x: 0, y: 0, width: 4, length: 10, turned: true })), found.rolls);
+
+	assert_eq!(Err(3), plan(roll, &[(3, 11, 2)]));
+	assert_eq!(0, plan(roll, &[]).unwrap().rolls.len());
+}
+
+#[test]
+fn plan_random_test() {
+	// A fixed xorshift, so the test is the same every time.
+	let mut state: u64 = 0x2545f4914f6cdd1d;
+	let mut random = |limit: u64| {
+		state ^= state << 13;
+		state ^= state >> 7;
+		state ^= state << 17;
+		state % limit + 1
+	};
+
+	for _ in 0..50 {
+		let roll = Roll { width: random(30) + 10, length: random(60) + 10 };
+		let count = random(40) as usize;
+		let side = roll.width.min(roll.length);
+		let sheets: Vec<(usize, u64, u64)> = (1..=count)
+			.map(|line| (line, random(side), random(side)))
+			.collect();
+		let found = plan(roll, &sheets).unwrap();
+
+		assert!(found.rolls.len() as u128 >= found.lower_bound());
+		assert_eq!(count, found.rolls.iter().map(Vec::len).sum::<usize>());
+		for cuts in &found.rolls {
+			for (i, cut) in cuts.iter().enumerate() {
+				assert!(cut.x + cut.width <= roll.width && cut.y + cut.length <= roll.length, "{:?}", cut);
+				for other in &cuts[i + 1..] {
+					let apart = cut.x + cut.width <= other.x || other.x + other.width <= cut.x
+						|| cut.y + cut.length <= other.y || other.y + other.length <= cut.y;
+					assert!(apart, "{:?} overlaps {:?}", cut, other);
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,234 @@
+//! Provides dimensions written with units, such as `2ft`, `3in` or `10.5cm`.
+//!
+//! Every dimension is read exactly as a whole number of micrometres, which every unit is, and the
+//! wrapping of each present is worked out from those. Only the amounts that are reported, for each
+//! present and in total, are converted into the base unit, the paper into square base units and the
+//! ribbon into base units, and rounded by the Rounding rule.
+//!
+//! A dimension without a unit is in the base unit, so changing the base unit changes the size of
+//! such a present rather than converting it. Lists of plain whole numbers give the same answers
+//! whatever the rounding.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Unit is a unit of length.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Unit {
+	Millimetre,
+	Centimetre,
+	Metre,
+	Inch,
+	Foot,
+}
+
+impl Unit {
+	/// Returns the length of the unit in micrometres.
+	pub fn micrometres(self) -> u128 {
+		match self {
+			Unit::Millimetre => 1000,
+			Unit::Centimetre => 10000,
+			Unit::Metre => 1000000,
+			Unit::Inch => 25400,
+			Unit::Foot => 304800,
+		}
+	}
+}
+
+impl FromStr for Unit {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Unit, String> {
+		match s {
+			"mm" => Ok(Unit::Millimetre),
+			"cm" => Ok(Unit::Centimetre),
+			"m" => Ok(Unit::Metre),
+			"in" => Ok(Unit::Inch),
+			"ft" => Ok(Unit::Foot),
+			_ => Err(format!("unknown unit {:?}, expected mm, cm, m, in or ft", s)),
+		}
+	}
+}
+
+impl fmt::Display for Unit {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let symbol = match *self {
+			Unit::Millimetre => "mm",
+			Unit::Centimetre => "cm",
+			Unit::Metre => "m",
+			Unit::Inch => "in",
+			Unit::Foot => "ft",
+		};
+		write!(f, "{}", symbol)
+	}
+}
+
+/// Rounding is how an amount that is not a whole number of base units is rounded.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Rounding {
+	/// Up to the next whole unit, so there is always enough paper.
+	Up,
+	/// Down to the previous whole unit.
+	Down,
+	/// To the nearest whole unit, with halves going up.
+	Nearest,
+}
+
+impl FromStr for Rounding {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Rounding, String> {
+		match s {
+			"up" => Ok(Rounding::Up),
+			"down" => Ok(Rounding::Down),
+			"nearest" => Ok(Rounding::Nearest),
+			_ => Err(format!("unknown rounding {:?}, expected up, down or nearest", s)),
+		}
+	}
+}
+
+impl fmt::Display for Rounding {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let name = match *self {
+			Rounding::Up => "up to",
+			Rounding::Down => "down to",
+			Rounding::Nearest => "to the nearest",
+		};
+		write!(f, "{}", name)
+	}
+}
+
+/// Units decides how the dimensions of a present are read.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Units {
+	pub base: Unit,
+	pub rounding: Rounding,
+}
+
+impl Default for Units {
+	/// Feet, as in the puzzle, rounded up.
+	fn default() -> Units {
+		Units { base: Unit::Foot, rounding: Rounding::Up }
+	}
+}
+
+impl Units {
+	/// Parses a single dimension, a number with an optional unit, as a length in micrometres.
+	///
+	/// Fails if the dimension is not a number greater than zero, or is not a whole number of
+	/// micrometres.
+	pub fn parse_dimension(&self, dimension: &str) -> Result<u64, String> {
+		let invalid = || format!("the dimension {:?} should be a number greater than zero, \
+			optionally followed by a unit such as 3in", dimension);
+
+		let split = dimension.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(dimension.len());
+		let (number, unit) = dimension.split_at(split);
+		let unit = if unit.is_empty() { self.base } else { Unit::from_str(unit)? };
+
+		// The number is read as `mantissa / 10^decimals`.
+		let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+		let digits = || whole.chars().chain(fraction.chars());
+		if digits().next().is_none() || !digits().all(|c| c.is_ascii_digit()) {
+			return Err(invalid());
+		}
+		let mantissa = u128::from_str(&format!("{}{}", whole, fraction)).map_err(|_| invalid())?;
+		if mantissa == 0 {
+			return Err(invalid());
+		}
+
+		let too_large = || format!("the dimension {:?} is too large", dimension);
+		let numerator = mantissa.checked_mul(unit.micrometres()).ok_or_else(too_large)?;
+		let denominator = 10u128.checked_pow(fraction.len() as u32).ok_or_else(too_large)?;
+
+		if numerator % denominator != 0 {
+			return Err(format!("the dimension {:?} is not a whole number of micrometres", dimension));
+		}
+		let micrometres = numerator / denominator;
+		if micrometres > u64::MAX as u128 {
+			return Err(too_large());
+		}
+		Ok(micrometres as u64)
+	}
+
+	/// Converts an amount in micrometres to the power of `power` into base units to the same power,
+	/// rounded by the rounding rule.
+	pub fn round(&self, amount: u128, power: u32) -> u128 {
+		let denominator = self.base.micrometres().pow(power);
+
+		match self.rounding {
+			Rounding::Down => amount / denominator,
+			Rounding::Up => amount.div_ceil(denominator),
+			Rounding::Nearest => amount / denominator + u128::from(amount % denominator * 2 >= denominator),
+		}
+	}
+
+	/// Writes a length in micrometres in base units, with at most four decimals.
+	pub fn format(&self, length: u64) -> String {
+		let base = self.base.micrometres();
+		let scaled = (length as u128 * 10000 + base / 2) / base;
+		if scaled.is_multiple_of(10000) {
+			(scaled / 10000).to_string()
+		} else {
+			let decimals = format!("{:04}", scaled % 10000);
+			format!("{}.{}", scaled / 10000, decimals.trim_end_matches('0'))
+		}
+	}
+}
+
+#[test]
+fn parse_dimension_test() {
+	let feet = Units::default();
+	assert_eq!(Ok(609600), feet.parse_dimension("2"));
+	assert_eq!(Ok(609600), feet.parse_dimension("2ft"));
+	assert_eq!(Ok(76200), feet.parse_dimension("3in"));
+	assert_eq!(Ok(762000), feet.parse_dimension("2.5"));
+	assert_eq!(Ok(101600), feet.parse_dimension("10.16cm"));
+	assert_eq!(Ok(1500000), feet.parse_dimension("1.5m"));
+	assert_eq!(Ok(1), feet.parse_dimension("0.001mm"));
+
+	// A dimension without a unit is in the base unit.
+	let inches = Units { base: Unit::Inch, rounding: Rounding::Nearest };
+	assert_eq!(Ok(50800), inches.parse_dimension("2"));
+	assert_eq!(Ok(12700), inches.parse_dimension(".5"));
+	assert_eq!(Ok(609600), inches.parse_dimension("2ft"));
+
+	let millimetres = Units { base: Unit::Millimetre, rounding: Rounding::Down };
+	assert_eq!(Ok(u64::MAX), millimetres.parse_dimension("18446744073709551.615"));
+	assert_eq!(Err("the dimension \"0.0005mm\" is not a whole number of micrometres".to_string()), feet.parse_dimension("0.0005mm"));
+
+	for invalid in &["", "0", "0.0", "-3", ".", "1.2.3", "3 in", "in", "+3"] {
+		assert!(feet.parse_dimension(invalid).is_err(), "{:?}", invalid);
+	}
+	assert_eq!(Err("unknown unit \"yd\", expected mm, cm, m, in or ft".to_string()), feet.parse_dimension("3yd"));
+	assert_eq!(Err("the dimension \"18446744073709551615\" is too large".to_string()), feet.parse_dimension("18446744073709551615"));
+	assert!(millimetres.parse_dimension("18446744073709551.616").is_err());
+	assert!(feet.parse_dimension("1000000000000000000000000000000000000000").is_err());
+}
+
+#[test]
+fn round_test() {
+	let foot = Unit::Foot.micrometres();
+	let rounded = |rounding: Rounding| {
+		let units = Units { base: Unit::Foot, rounding };
+		// 3in, 18in, and half a square foot.
+		(units.round(foot / 4, 1), units.round(foot * 3 / 2, 1), units.round(foot * foot / 2, 2))
+	};
+	assert_eq!((1, 2, 1), rounded(Rounding::Up));
+	assert_eq!((0, 1, 0), rounded(Rounding::Down));
+	assert_eq!((0, 2, 1), rounded(Rounding::Nearest));
+
+	assert_eq!(2, Units::default().round(foot * foot * foot * 2, 3));
+	assert_eq!(0, Units::default().round(0, 2));
+}
+
+#[test]
+fn format_test() {
+	let feet = Units::default();
+	assert_eq!("2", feet.format(609600));
+	assert_eq!("0.25", feet.format(76200));
+	assert_eq!("1.0833", feet.format(330200));
+
+	let inches = Units { base: Unit::Inch, rounding: Rounding::Up };
+	assert_eq!("4", inches.format(101600));
+	assert_eq!("3.937", inches.format(100000));
+}
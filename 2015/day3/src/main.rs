@@ -5,7 +5,7 @@ fn main() {
 
 	let mut args = std::env::args();
 	let num_santas = if args.len() > 1 {
-		i32::from_str(&args.nth(1).unwrap()).unwrap()
+		usize::from_str(&args.nth(1).unwrap()).unwrap()
 	} else {
 		1
 	};
@@ -21,58 +21,41 @@ fn main() {
 	println!("Distinct houses visisted: {}", distinct_houses);
 }
 
-fn count_distinct_houses(directions: &str, num_santas: i32) -> i32 {
+/// Counts the houses visited at least once by `num_santas` santas all starting at the same house,
+/// who take turns following the directions. Characters that are not directions are skipped and do
+/// not use up a turn.
+fn count_distinct_houses(directions: &str, num_santas: usize) -> usize {
 	use std::collections::HashMap;
 
-	let mut grid = HashMap::new();
-	let mut santas = Vec::new();
-
-	for _ in 0..num_santas {
-		santas.push((0,0));
+	if num_santas == 0 {
+		return 0;
 	}
 
-	grid.insert((0,0), num_santas);
+	let mut grid = HashMap::new();
+	let mut santas = vec!((0, 0); num_santas);
+
+	grid.insert((0, 0), num_santas);
 
-	let mut current_santa = 0;
+	let mut step = 0;
 
 	for direction in directions.chars() {
+		let santa = &mut santas[step % num_santas];
 
-		let current_pos = santas.pop().unwrap();
-
-		let (x, y) = current_pos;
+		let (x, y) = *santa;
 		let next = match direction {
-			'^' => Some((x, y + 1)),
-			'<' => Some((x - 1, y)),
-			'>' => Some((x + 1, y)),
-			'v' => Some((x, y - 1)),
-			_   => None,
-		};
-
-		match next {
-			Some(pos) => {
-				// Update the accumulator
-				santas.insert(0, pos);	// Need to insert at beginning instead of push to end.
-
-				// Update the grid
-				if !grid.contains_key(&pos) {
-					grid.insert(pos, 1);
-				} else {
-					if let Some(x) = grid.get_mut(&pos) {
-						*x = *x + 1;
-					}
-				}
-			}
-			None => {}
+			'^' => (x, y + 1),
+			'<' => (x - 1, y),
+			'>' => (x + 1, y),
+			'v' => (x, y - 1),
+			_   => continue,
 		};
 
-		if current_santa + 1 >= santas.len() {
-			current_santa = 0;
-		} else {
-			current_santa = current_santa + 1;
-		}
+		*santa = next;
+		*grid.entry(next).or_insert(0) += 1;
+		step += 1;
 	}
 
-	grid.len() as i32
+	grid.len()
 }
 
 #[test]
@@ -107,4 +90,15 @@ fn count_distinct_houses_multisanta_test() {
 	let input3 = "^v^v^v^v^v ";
 	let expect3 = 11;
 	assert_eq!(expect3, count_distinct_houses(input3, num_santas));
-}
\ No newline at end of file
+}
+#[test]
+fn count_distinct_houses_many_santas_test() {
+	// Every santa takes a single step away from the start.
+	assert_eq!(5, count_distinct_houses("^>v<", 4));
+	assert_eq!(2, count_distinct_houses("^^^^^^", 1000));
+
+	// Characters that are not directions do not use up a turn.
+	assert_eq!(3, count_distinct_houses("^ v\n", 2));
+
+	assert_eq!(0, count_distinct_houses("^v", 0));
+}
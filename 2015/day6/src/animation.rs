@@ -0,0 +1,260 @@
+//! Provides frame by frame recording of the lights as a program runs.
+//!
+//! Frames are either written as a numbered sequence of images, such as `show-0001.ppm`,
+//! `show-0002.ppm` and so on, or as the frames of a single looping animated GIF.
+
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+
+use grid::Grid;
+use image::{Format, Image};
+
+/// The time each frame of a GIF is shown for, in hundredths of a second.
+const GIF_FRAME_DELAY: u16 = 10;
+
+/// A Recorder saves a frame after every so many instructions of a program.
+pub struct Recorder {
+	every: usize,
+	downscale: usize,
+	sink: Sink,
+	frames: usize,
+}
+
+enum Sink {
+	Gif(GifWriter<io::BufWriter<File>>),
+	Files(PathBuf),
+}
+
+impl Recorder {
+	/// Creates a recorder which saves a frame after every `every` instructions, shrinking each
+	/// frame by `downscale` in both directions.
+	///
+	/// If `path` ends in `.gif` the frames make up a single animation, otherwise `path` names a
+	/// sequence of images and each frame is numbered.
+	pub fn create(path: &str, every: usize, downscale: usize) -> Result<Recorder, String> {
+		let path = Path::new(path);
+
+		let sink = if path.extension().and_then(|e| e.to_str()).map(|e| e.eq_ignore_ascii_case("gif")) == Some(true) {
+			let f = File::create(path).map_err(|e| format!("cannot create {}: {}", path.display(), e))?;
+			Sink::Gif(GifWriter::new(io::BufWriter::new(f)))
+		} else {
+			Format::from_path(path).map_err(|_| format!("{} should end in .gif, .pgm, .ppm or .png", path.display()))?;
+			Sink::Files(path.to_path_buf())
+		};
+
+		Ok(Recorder { every, downscale, sink, frames: 0 })
+	}
+
+	/// Records the grid after `step` instructions have been run, if a frame is due.
+	pub fn record(&mut self, step: usize, grid: &Grid) -> Result<(), String> {
+		if step.is_multiple_of(self.every) {
+			self.frame(grid)?;
+		}
+
+		Ok(())
+	}
+
+	/// Records the final grid after `steps` instructions, unless it was just recorded, and returns
+	/// the number of frames saved.
+	pub fn finish(mut self, steps: usize, grid: &Grid) -> Result<usize, String> {
+		if !steps.is_multiple_of(self.every) || self.frames == 0 {
+			self.frame(grid)?;
+		}
+
+		if let Sink::Gif(writer) = self.sink {
+			writer.finish().map_err(|e| format!("cannot write animation: {}", e))?;
+		}
+
+		Ok(self.frames)
+	}
+
+	fn frame(&mut self, grid: &Grid) -> Result<(), String> {
+		let image = Image::render(grid).downscale(self.downscale);
+		self.frames += 1;
+
+		match self.sink {
+			Sink::Gif(ref mut writer) => {
+				writer.frame(&image).map_err(|e| format!("cannot write animation: {}", e))
+			},
+			Sink::Files(ref path) => image.save(numbered(path, self.frames)),
+		}
+	}
+}
+
+/// Returns `path` with a frame number added to the end of its file name.
+fn numbered(path: &Path, frame: usize) -> PathBuf {
+	let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+	let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+
+	path.with_file_name(format!("{}-{:04}.{}", stem, frame, extension))
+}
+
+#[test]
+fn numbered_test() {
+	assert_eq!(PathBuf::from("frames/show-0012.ppm"), numbered(Path::new("frames/show.ppm"), 12));
+}
+
+#[test]
+fn recorder_test() {
+	use std::fs;
+
+	use grid::Mode;
+	use region::Region;
+	use Instructions;
+
+	let dir = ::std::env::temp_dir().join("day6_recorder");
+	let _ = fs::remove_dir_all(&dir);
+	fs::create_dir_all(&dir).unwrap();
+
+	let mut grid = Grid::new(Mode::OnOff, 4, 4);
+	let path = dir.join("show.pgm").display().to_string();
+	let mut recorder = Recorder::create(&path, 2, 2).unwrap();
+	for step in 1..4 {
+		grid.apply(&Instructions::Toggle(Region::Rectangle((0, 0), (step, step))));
+		recorder.record(step as usize, &grid).unwrap();
+	}
+	assert_eq!(2, recorder.finish(3, &grid).unwrap());
+
+	let mut second = Vec::new();
+	File::open(dir.join("show-0002.pgm")).unwrap().read_to_end(&mut second).unwrap();
+	assert_eq!(b"P5\n2 2\n255\n\xff\x7f\x7f\xbf".to_vec(), second);
+	assert!(!dir.join("show-0003.pgm").exists());
+
+	let path = dir.join("show.gif").display().to_string();
+	let mut recorder = Recorder::create(&path, 1, 1).unwrap();
+	recorder.record(1, &grid).unwrap();
+	assert_eq!(1, recorder.finish(1, &grid).unwrap());
+
+	let mut gif = Vec::new();
+	File::open(dir.join("show.gif")).unwrap().read_to_end(&mut gif).unwrap();
+	assert_eq!(b"GIF89a".to_vec(), gif[..6].to_vec());
+	assert_eq!(Some(&0x3b), gif.last());
+
+	assert!(Recorder::create(&dir.join("show.txt").display().to_string(), 1, 1).is_err());
+}
+
+/// The most literal codes written between clear codes, which keeps every code 9 bits wide.
+const GIF_CODES_PER_CLEAR: usize = 254;
+
+/// A GifWriter writes 8 bit grayscale frames to a looping animated GIF.
+///
+/// The image data is LZW coded without any actual compression: every pixel is written as a literal
+/// code, and the code table is cleared before it grows enough to need wider codes.
+struct GifWriter<W: Write> {
+	w: W,
+	started: bool,
+}
+
+impl<W: Write> GifWriter<W> {
+	fn new(w: W) -> GifWriter<W> {
+		GifWriter { w, started: false }
+	}
+
+	/// Writes the header, taking the size of the animation from the first frame.
+	fn start(&mut self, width: u16, height: u16) -> io::Result<()> {
+		self.w.write_all(b"GIF89a")?;
+		self.w.write_all(&width.to_le_bytes())?;
+		self.w.write_all(&height.to_le_bytes())?;
+		// A global colour table of 256 entries, with no background colour or aspect ratio.
+		self.w.write_all(&[0xf7, 0, 0])?;
+		for level in 0..256 {
+			self.w.write_all(&[level as u8; 3])?;
+		}
+
+		// Loop forever.
+		self.w.write_all(b"\x21\xff\x0bNETSCAPE2.0\x03\x01\x00\x00\x00")?;
+
+		self.started = true;
+		Ok(())
+	}
+
+	fn frame(&mut self, image: &Image) -> io::Result<()> {
+		if image.width > u16::MAX as usize || image.height > u16::MAX as usize {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput,
+				format!("a {}x{} frame is too large for a GIF, try --downscale", image.width, image.height)));
+		}
+
+		let (width, height) = (image.width as u16, image.height as u16);
+		if !self.started {
+			self.start(width, height)?;
+		}
+
+		// A graphic control extension setting the delay before the next frame.
+		self.w.write_all(&[0x21, 0xf9, 4, 0])?;
+		self.w.write_all(&GIF_FRAME_DELAY.to_le_bytes())?;
+		self.w.write_all(&[0, 0])?;
+
+		// An image descriptor covering the whole screen, using the global colour table.
+		self.w.write_all(&[0x2c, 0, 0, 0, 0])?;
+		self.w.write_all(&width.to_le_bytes())?;
+		self.w.write_all(&height.to_le_bytes())?;
+		self.w.write_all(&[0])?;
+
+		const CLEAR: u32 = 256;
+		const END: u32 = 257;
+
+		let mut bits = BitWriter::new();
+		for chunk in image.pixels.chunks(GIF_CODES_PER_CLEAR) {
+			bits.write(CLEAR, 9);
+			for &pixel in chunk {
+				bits.write(pixel as u32, 9);
+			}
+		}
+		bits.write(END, 9);
+
+		// The minimum code size, then the data in blocks of at most 255 bytes.
+		self.w.write_all(&[8])?;
+		for block in bits.finish().chunks(255) {
+			self.w.write_all(&[block.len() as u8])?;
+			self.w.write_all(block)?;
+		}
+		self.w.write_all(&[0])
+	}
+
+	fn finish(mut self) -> io::Result<()> {
+		self.w.write_all(&[0x3b])?;
+		self.w.flush()
+	}
+}
+
+/// A BitWriter packs codes into bytes, least significant bit first.
+struct BitWriter {
+	bytes: Vec<u8>,
+	buffer: u32,
+	count: u32,
+}
+
+impl BitWriter {
+	fn new() -> BitWriter {
+		BitWriter { bytes: Vec::new(), buffer: 0, count: 0 }
+	}
+
+	fn write(&mut self, code: u32, width: u32) {
+		self.buffer |= code << self.count;
+		self.count += width;
+
+		while self.count >= 8 {
+			self.bytes.push(self.buffer as u8);
+			self.buffer >>= 8;
+			self.count -= 8;
+		}
+	}
+
+	fn finish(mut self) -> Vec<u8> {
+		if self.count > 0 {
+			self.bytes.push(self.buffer as u8);
+		}
+		self.bytes
+	}
+}
+
+#[test]
+fn bit_writer_test() {
+	let mut bits = BitWriter::new();
+	bits.write(256, 9);
+	bits.write(255, 9);
+	bits.write(257, 9);
+	assert_eq!(vec!(0x00, 0xff, 0x05, 0x04), bits.finish());
+}
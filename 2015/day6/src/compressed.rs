@@ -0,0 +1,263 @@
+//! Provides an evaluator that works on compressed coordinates rather than individual lights.
+//!
+//! Only the edges of the instruction rectangles matter: between two neighbouring edges every light
+//! receives exactly the same instructions. Splitting the plane along every edge gives a grid of
+//! blocks, each of which can stand in for all of the lights it covers. Running the instructions
+//! against the blocks and weighting each block by its area gives the same answer as the full grid,
+//! while the work depends only on the number of instructions and not on the size of the grid.
+
+use std::ops::Range;
+
+use grid::{Effect, Mode};
+use {Coordinate, Instructions};
+
+type Rectangle = (Coordinate, Coordinate);
+
+/// A rectangle as ranges of block columns and rows, along with the effect applied to it.
+type BlockRectangle = (Effect, Range<usize>, Range<usize>);
+
+/// Returns the sorted, distinct block edges along one axis.
+///
+/// A rectangle covering `start..end` inclusive contributes the edges `start` and `end + 1`.
+fn edges<F: Fn(&Rectangle) -> (i64, i64)>(rectangles: &[Rectangle], range: F) -> Vec<u64> {
+	let mut edges: Vec<u64> = rectangles.iter()
+		.flat_map(|rectangle| {
+			let (start, end) = range(rectangle);
+			vec!(start as u64, end as u64 + 1)
+		})
+		.collect();
+
+	edges.sort();
+	edges.dedup();
+	edges
+}
+
+/// Returns the indices of the blocks covering `start..end` inclusive.
+fn blocks(edges: &[u64], start: i64, end: i64) -> Range<usize> {
+	// Both edges are known to be present, since they came from the instructions.
+	let first = edges.binary_search(&(start as u64)).unwrap();
+	let last = edges.binary_search(&(end as u64 + 1)).unwrap();
+
+	first..last
+}
+
+/// Blocks is a program translated onto compressed coordinates.
+pub struct Blocks {
+	xs: Vec<u64>,
+	ys: Vec<u64>,
+	/// The rectangles of each instruction.
+	instructions: Vec<Vec<BlockRectangle>>,
+}
+
+impl Blocks {
+	/// Splits a `width` by `height` grid into blocks along the edges of every instruction.
+	///
+	/// Fails if an instruction lies outside of the grid.
+	pub fn new(instructions: &[Instructions], mode: Mode, width: usize, height: usize) -> Result<Blocks, String> {
+		let instructions = ::validate_program(instructions, width, height)?;
+
+		// Every region is split into the rectangles it covers.
+		let rectangles: Vec<Vec<Rectangle>> = instructions.iter()
+			.map(|instruction| instruction.region().rectangles())
+			.collect();
+		let bounds: Vec<Rectangle> = rectangles.iter().flat_map(|r| r.iter().cloned()).collect();
+
+		let xs = edges(&bounds, |&(start, end)| (start.0, end.0));
+		let ys = edges(&bounds, |&(start, end)| (start.1, end.1));
+
+		let instructions = instructions.iter().zip(rectangles)
+			.map(|(instruction, rectangles)| {
+				let effect = Effect::of(instruction, mode);
+				rectangles.iter()
+					.map(|&(start, end)| (effect, blocks(&xs, start.0, end.0), blocks(&ys, start.1, end.1)))
+					.collect()
+			})
+			.collect();
+
+		Ok(Blocks { xs, ys, instructions })
+	}
+
+	fn width(&self) -> usize {
+		self.xs.len().saturating_sub(1)
+	}
+
+	fn height(&self) -> usize {
+		self.ys.len().saturating_sub(1)
+	}
+
+	/// Returns the number of instructions in the program.
+	pub fn len(&self) -> usize {
+		self.instructions.len()
+	}
+
+	/// Returns the value of each block before any instructions are run, row by row.
+	pub fn dark(&self) -> Vec<u32> {
+		vec!(0; self.width() * self.height())
+	}
+
+	/// Runs every instruction and returns the value of each block, row by row.
+	pub fn run(&self) -> Vec<u32> {
+		let mut values = self.dark();
+		for i in 0..self.instructions.len() {
+			self.apply(i, &mut values);
+		}
+
+		values
+	}
+
+	/// Applies the instruction at index `i` to the value of each block.
+	pub fn apply(&self, i: usize, values: &mut [u32]) {
+		let width = self.width();
+
+		for &(effect, ref columns, ref rows) in &self.instructions[i] {
+			for y in rows.clone() {
+				let row = y * width;
+				for block in &mut values[row + columns.start..row + columns.end] {
+					*block = effect.apply(*block);
+				}
+			}
+		}
+	}
+
+	/// Returns the number of lights lit in on/off mode, or the total brightness in brightness mode,
+	/// given the value of each block.
+	pub fn total(&self, values: &[u32]) -> u64 {
+		let width = self.width();
+
+		let mut total = 0;
+		for y in 0..self.height() {
+			let height = self.ys[y + 1] - self.ys[y];
+			for x in 0..width {
+				let area = (self.xs[x + 1] - self.xs[x]) * height;
+				total += values[y * width + x] as u64 * area;
+			}
+		}
+
+		total
+	}
+}
+
+/// Runs the instructions over compressed coordinates of a `width` by `height` grid and returns the
+/// number of lights lit in on/off mode, or the total brightness in brightness mode.
+///
+/// Fails without running anything if an instruction lies outside of the grid.
+pub fn run_compressed(instructions: &[Instructions], mode: Mode, width: usize, height: usize)
+	-> Result<u64, String> {
+
+	let blocks = Blocks::new(instructions, mode, width, height)?;
+	Ok(blocks.total(&blocks.run()))
+}
+
+/// Returns a repeatable list of pseudo random instructions within a `size` by `size` grid.
+#[cfg(test)]
+pub fn random_instructions(count: usize, size: u32, seed: u64) -> Vec<Instructions> {
+	use region::Region;
+
+	let mut state = seed;
+	let mut next = |bound: u32| {
+		// A linear congruential generator is plenty for spreading rectangles around.
+		state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+		((state >> 33) % bound as u64) as u32
+	};
+
+	(0..count).map(|_| {
+		let (x1, x2) = (next(size), next(size));
+		let (y1, y2) = (next(size), next(size));
+		let start = (x1.min(x2) as i64, y1.min(y2) as i64);
+		let end = (x1.max(x2) as i64, y1.max(y2) as i64);
+
+		let region = Region::Rectangle(start, end);
+		match next(3) {
+			0 => Instructions::TurnOn(region),
+			1 => Instructions::TurnOff(region),
+			_ => Instructions::Toggle(region),
+		}
+	}).collect()
+}
+
+#[cfg(test)]
+fn run_grid(instructions: &[Instructions], mode: Mode, width: usize, height: usize) -> u64 {
+	let mut grid = ::grid::Grid::new(mode, width, height);
+	for instruction in instructions {
+		grid.apply(instruction);
+	}
+	grid.total()
+}
+
+#[test]
+fn run_compressed_test() {
+	use region::Region;
+
+	let test_case = vec!(
+		Instructions::TurnOn(Region::Rectangle((0,0), (999,999))),
+		Instructions::Toggle(Region::Rectangle((0,0), (999,0))),
+		Instructions::TurnOff(Region::Rectangle((499,499),(500,500)))
+	);
+
+	assert_eq!(Ok(998996), run_compressed(&test_case, Mode::OnOff, 1000, 1000));
+	assert_eq!(Ok(1001996), run_compressed(&test_case, Mode::Brightness, 1000, 1000));
+	assert_eq!(Ok(0), run_compressed(&[], Mode::OnOff, 1000, 1000));
+	assert!(run_compressed(&test_case, Mode::OnOff, 1000, 999).is_err());
+
+	for seed in 0..10 {
+		let instructions = random_instructions(50, 300, seed);
+		for &mode in &[Mode::OnOff, Mode::Brightness] {
+			assert_eq!(Ok(run_grid(&instructions, mode, 300, 300)), run_compressed(&instructions, mode, 300, 300));
+		}
+	}
+
+	let test_case = vec!(
+		Instructions::Set(7, Region::Rectangle((0,0), (49,49))),
+		Instructions::Dim(3, Region::Outline((5,5), (40,30))),
+		Instructions::Brighten(2, Region::Line((0,49), (49,0))),
+		Instructions::Toggle(Region::Circle((25,25), 10)),
+		Instructions::Dim(0, Region::Point((3,3))),
+		Instructions::Set(0, Region::Circle((10,40), 5)),
+	);
+	for &mode in &[Mode::OnOff, Mode::Brightness] {
+		assert_eq!(Ok(run_grid(&test_case, mode, 50, 50)), run_compressed(&test_case, mode, 50, 50));
+	}
+}
+
+#[test]
+fn run_compressed_huge_test() {
+	use region::Region;
+
+	let test_case = vec!(
+		Instructions::TurnOn(Region::Rectangle((0,0), (999999,999999))),
+		Instructions::Toggle(Region::Rectangle((0,0), (999999,0))),
+		Instructions::TurnOff(Region::Rectangle((499999,499999),(500000,500000)))
+	);
+
+	assert_eq!(Ok(1000000 * 1000000 - 1000000 - 4), run_compressed(&test_case, Mode::OnOff, 1000000, 1000000));
+	assert_eq!(Ok(1000000 * 1000000 + 2000000 - 4), run_compressed(&test_case, Mode::Brightness, 1000000, 1000000));
+}
+
+#[cfg(test)]
+fn puzzle_input() -> Vec<Instructions> {
+	::parse_program(include_str!("../input.txt"))
+}
+
+#[bench]
+fn bench_grid_puzzle_input(b: &mut ::test::Bencher) {
+	let instructions = puzzle_input();
+	b.iter(|| run_grid(&instructions, Mode::Brightness, 1000, 1000));
+}
+
+#[bench]
+fn bench_compressed_puzzle_input(b: &mut ::test::Bencher) {
+	let instructions = puzzle_input();
+	b.iter(|| run_compressed(&instructions, Mode::Brightness, 1000, 1000));
+}
+
+#[bench]
+fn bench_grid_large_rectangles(b: &mut ::test::Bencher) {
+	let instructions = random_instructions(100, 4000, 7);
+	b.iter(|| run_grid(&instructions, Mode::Brightness, 4000, 4000));
+}
+
+#[bench]
+fn bench_compressed_large_rectangles(b: &mut ::test::Bencher) {
+	let instructions = random_instructions(100, 4000, 7);
+	b.iter(|| run_compressed(&instructions, Mode::Brightness, 4000, 4000));
+}
@@ -0,0 +1,116 @@
+//! Provides a measure of how much each instruction matters to the final lights.
+//!
+//! The contribution of an instruction is how much the answer would drop if that instruction were
+//! left out of the program. An instruction can also leave the answer alone yet still change which
+//! lights end up on, so the final grids are compared as well as the answers.
+//!
+//! Each instruction is left out in turn on the compressed block grid of the whole program. Since
+//! the blocks are the same for every run, the final grids can be compared block by block. Runs
+//! start from a copy of the blocks saved before the instruction being left out, rather than from
+//! the start of the program.
+
+use compressed::Blocks;
+use grid::Mode;
+use Instructions;
+
+/// The number of instructions between saved copies of the blocks.
+const CHECKPOINT_INTERVAL: usize = 16;
+
+/// Contribution is what a single instruction adds to the result of a program.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Contribution {
+	/// The difference between the answer of the whole program and the answer without this
+	/// instruction. It is negative when leaving the instruction out would raise the answer.
+	pub difference: i64,
+	/// Whether leaving the instruction out would change the final state of any light.
+	pub changes_grid: bool,
+}
+
+/// Works out the contribution of each instruction, in the order of the program.
+///
+/// Fails if an instruction lies outside of the grid.
+pub fn contributions(instructions: &[Instructions], mode: Mode, width: usize, height: usize)
+	-> Result<Vec<Contribution>, String> {
+
+	let blocks = Blocks::new(instructions, mode, width, height)?;
+
+	let mut checkpoints = Vec::new();
+	let mut values = blocks.dark();
+	for i in 0..blocks.len() {
+		if i.is_multiple_of(CHECKPOINT_INTERVAL) {
+			checkpoints.push(values.clone());
+		}
+		blocks.apply(i, &mut values);
+	}
+
+	let total = blocks.total(&values);
+
+	Ok((0..blocks.len()).map(|skipped| {
+		let start = skipped / CHECKPOINT_INTERVAL * CHECKPOINT_INTERVAL;
+		let mut without = checkpoints[skipped / CHECKPOINT_INTERVAL].clone();
+		for i in start..blocks.len() {
+			if i != skipped {
+				blocks.apply(i, &mut without);
+			}
+		}
+
+		Contribution {
+			difference: total as i64 - blocks.total(&without) as i64,
+			changes_grid: without != values,
+		}
+	}).collect())
+}
+
+#[test]
+fn contributions_test() {
+	use region::Region;
+
+	let instructions = vec!(
+		Instructions::TurnOn(Region::Rectangle((0, 0), (9, 9))),
+		Instructions::TurnOn(Region::Rectangle((0, 0), (4, 4))),
+		Instructions::Toggle(Region::Rectangle((0, 0), (4, 9))),
+		Instructions::Toggle(Region::Rectangle((5, 0), (9, 9))),
+		Instructions::TurnOff(Region::Rectangle((0, 0), (0, 0))),
+	);
+
+	let expected = vec!(
+		Contribution { difference: -75, changes_grid: true },
+		Contribution { difference: 0, changes_grid: false },
+		Contribution { difference: -49, changes_grid: true },
+		Contribution { difference: -50, changes_grid: true },
+		Contribution { difference: 0, changes_grid: false },
+	);
+	assert_eq!(Ok(expected), contributions(&instructions, Mode::OnOff, 10, 10));
+
+	let expected = vec!(
+		Contribution { difference: 100, changes_grid: true },
+		Contribution { difference: 25, changes_grid: true },
+		Contribution { difference: 100, changes_grid: true },
+		Contribution { difference: 100, changes_grid: true },
+		Contribution { difference: -1, changes_grid: true },
+	);
+	assert_eq!(Ok(expected), contributions(&instructions, Mode::Brightness, 10, 10));
+
+	assert_eq!(Ok(vec!()), contributions(&[], Mode::OnOff, 10, 10));
+	assert!(contributions(&instructions, Mode::OnOff, 9, 10).is_err());
+}
+
+#[test]
+fn contributions_random_test() {
+	use compressed::random_instructions;
+
+	let instructions = random_instructions(40, 50, 11);
+	for &mode in &[Mode::OnOff, Mode::Brightness] {
+		let found = contributions(&instructions, mode, 50, 50).unwrap();
+		let all = ::light_grid(&instructions, mode, 50, 50).unwrap();
+
+		for (i, contribution) in found.iter().enumerate() {
+			let mut without = instructions.clone();
+			without.remove(i);
+			let grid = ::light_grid(&without, mode, 50, 50).unwrap();
+
+			assert_eq!(all.total() as i64 - grid.total() as i64, contribution.difference);
+			assert_eq!(all != grid, contribution.changes_grid);
+		}
+	}
+}
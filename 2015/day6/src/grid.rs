@@ -0,0 +1,432 @@
+//! Provides the light grid that instructions are run against.
+//!
+//! The grid can be read in one of two ways. In on/off mode each light is either lit or not, and
+//! the lights are packed 64 to a word so that a row of a rectangle is updated a word at a time. In
+//! brightness mode each light has its own brightness which instructions raise and lower.
+
+use std::fmt;
+use std::str::FromStr;
+
+use {Coordinate, Instructions};
+
+/// Mode selects how instructions are interpreted.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Mode {
+	/// `turn on` and `turn off` set lights, `toggle` flips them.
+	OnOff,
+	/// `turn on` adds 1, `turn off` takes away 1 down to a minimum of 0, and `toggle` adds 2.
+	Brightness,
+}
+
+impl FromStr for Mode {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Mode, String> {
+		match s {
+			"on-off" => Ok(Mode::OnOff),
+			"brightness" => Ok(Mode::Brightness),
+			_ => Err(format!("unknown mode {:?}, expected on-off or brightness", s)),
+		}
+	}
+}
+
+impl fmt::Display for Mode {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			Mode::OnOff => write!(f, "on-off"),
+			Mode::Brightness => write!(f, "brightness"),
+		}
+	}
+}
+
+const WORD_BITS: usize = 64;
+
+/// The most lights a grid may hold, so that the brightness of every light can be addressed.
+pub const MAX_LIGHTS: usize = isize::MAX as usize / 4;
+
+/// Returns the number of lights in a `width` by `height` grid, or `None` if it is more than
+/// `MAX_LIGHTS`.
+pub fn lights(width: usize, height: usize) -> Option<usize> {
+	width.checked_mul(height).filter(|&lights| lights <= MAX_LIGHTS)
+}
+
+/// A BitGrid is a grid of lights that are either on or off, stored one bit per light.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BitGrid {
+	width: usize,
+	height: usize,
+	/// The number of words used by each row.
+	stride: usize,
+	words: Vec<u64>,
+}
+
+impl BitGrid {
+	/// Creates a grid with every light off. Panics if it would hold more than `MAX_LIGHTS`.
+	pub fn new(width: usize, height: usize) -> BitGrid {
+		lights(width, height).expect("the grid holds too many lights");
+		let stride = width.div_ceil(WORD_BITS);
+
+		BitGrid {
+			width,
+			height,
+			stride,
+			words: vec!(0; stride * height),
+		}
+	}
+
+	pub fn width(&self) -> usize {
+		self.width
+	}
+
+	pub fn height(&self) -> usize {
+		self.height
+	}
+
+	/// Returns whether the light at `(x, y)` is on.
+	pub fn get(&self, x: usize, y: usize) -> bool {
+		let word = self.words[y * self.stride + x / WORD_BITS];
+		word & (1 << (x % WORD_BITS)) != 0
+	}
+
+	/// Turns the light at `(x, y)` on or off.
+	pub fn set(&mut self, x: usize, y: usize, on: bool) {
+		let word = &mut self.words[y * self.stride + x / WORD_BITS];
+		if on {
+			*word |= 1 << (x % WORD_BITS);
+		} else {
+			*word &= !(1 << (x % WORD_BITS));
+		}
+	}
+
+	/// Turns on every light in the inclusive rectangle from `start` to `end`.
+	pub fn turn_on(&mut self, start: Coordinate, end: Coordinate) {
+		self.update(start, end, |word, mask| word | mask);
+	}
+
+	/// Turns off every light in the inclusive rectangle from `start` to `end`.
+	pub fn turn_off(&mut self, start: Coordinate, end: Coordinate) {
+		self.update(start, end, |word, mask| word & !mask);
+	}
+
+	/// Toggles every light in the inclusive rectangle from `start` to `end`.
+	pub fn toggle(&mut self, start: Coordinate, end: Coordinate) {
+		self.update(start, end, |word, mask| word ^ mask);
+	}
+
+	/// Returns the number of lights that are on.
+	pub fn count(&self) -> u64 {
+		self.words.iter().map(|word| word.count_ones() as u64).sum()
+	}
+
+	/// Applies `op` to each word of each row of the rectangle, along with a mask of the bits in
+	/// that word which lie inside the rectangle.
+	fn update<F: Fn(u64, u64) -> u64>(&mut self, start: Coordinate, end: Coordinate, op: F) {
+		let (start_x, start_y) = (start.0 as usize, start.1 as usize);
+		let (end_x, end_y) = (end.0 as usize, end.1 as usize);
+
+		let first = start_x / WORD_BITS;
+		let last = end_x / WORD_BITS;
+
+		for y in start_y..end_y + 1 {
+			let row = y * self.stride;
+
+			for i in first..last + 1 {
+				let low = if i == first { start_x % WORD_BITS } else { 0 };
+				let high = if i == last { end_x % WORD_BITS } else { WORD_BITS - 1 };
+				let mask = (!0u64 >> (WORD_BITS - 1 - high)) & (!0u64 << low);
+
+				self.words[row + i] = op(self.words[row + i], mask);
+			}
+		}
+	}
+}
+
+#[test]
+fn lights_test() {
+	assert_eq!(Some(1000000), lights(1000, 1000));
+	assert_eq!(Some(MAX_LIGHTS), lights(MAX_LIGHTS, 1));
+	assert_eq!(None, lights(MAX_LIGHTS, 2));
+	assert_eq!(None, lights(usize::MAX, usize::MAX));
+}
+
+#[test]
+fn bit_grid_test() {
+	let mut grid = BitGrid::new(200, 3);
+
+	grid.turn_on((10, 0), (150, 1));
+	assert_eq!(2 * 141, grid.count());
+	assert!(!grid.get(9, 0));
+	assert!(grid.get(10, 0));
+	assert!(grid.get(63, 1));
+	assert!(grid.get(64, 1));
+	assert!(grid.get(150, 1));
+	assert!(!grid.get(151, 1));
+	assert!(!grid.get(10, 2));
+
+	grid.toggle((0, 1), (199, 2));
+	assert_eq!(141 + 59 + 200, grid.count());
+	assert!(!grid.get(10, 1));
+	assert!(grid.get(199, 1));
+
+	grid.turn_off((0, 0), (199, 2));
+	assert_eq!(0, grid.count());
+
+	grid.set(130, 2, true);
+	grid.set(131, 2, true);
+	grid.set(130, 2, false);
+	assert_eq!(1, grid.count());
+	assert!(grid.get(131, 2));
+}
+
+/// A BrightnessGrid is a grid of lights that each have a brightness of zero or more.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BrightnessGrid {
+	width: usize,
+	height: usize,
+	cells: Vec<u32>,
+}
+
+impl BrightnessGrid {
+	/// Creates a grid with every light at a brightness of zero. Panics if it would hold more than
+	/// `MAX_LIGHTS`.
+	pub fn new(width: usize, height: usize) -> BrightnessGrid {
+		BrightnessGrid {
+			width,
+			height,
+			cells: vec!(0; lights(width, height).expect("the grid holds too many lights")),
+		}
+	}
+
+	/// Returns the brightness of the light at `(x, y)`.
+	pub fn get(&self, x: usize, y: usize) -> u32 {
+		self.cells[y * self.width + x]
+	}
+
+	/// Adds `amount` to the brightness of every light in the inclusive rectangle, to a maximum of
+	/// `u32::MAX`.
+	pub fn brighten(&mut self, start: Coordinate, end: Coordinate, amount: u32) {
+		self.update(start, end, |cell| cell.saturating_add(amount));
+	}
+
+	/// Takes `amount` away from the brightness of every light in the inclusive rectangle, to a
+	/// minimum of zero.
+	pub fn dim(&mut self, start: Coordinate, end: Coordinate, amount: u32) {
+		self.update(start, end, |cell| cell.saturating_sub(amount));
+	}
+
+	/// Sets the brightness of every light in the inclusive rectangle to `amount`.
+	pub fn set(&mut self, start: Coordinate, end: Coordinate, amount: u32) {
+		self.update(start, end, |_| amount);
+	}
+
+	/// Returns the brightness of all of the lights combined.
+	pub fn total(&self) -> u64 {
+		self.cells.iter().map(|&cell| cell as u64).sum()
+	}
+
+	/// Returns the brightness of the brightest light.
+	pub fn max(&self) -> u32 {
+		self.cells.iter().cloned().max().unwrap_or(0)
+	}
+
+	fn update<F: Fn(u32) -> u32>(&mut self, start: Coordinate, end: Coordinate, op: F) {
+		let (start_x, start_y) = (start.0 as usize, start.1 as usize);
+		let (end_x, end_y) = (end.0 as usize, end.1 as usize);
+
+		for y in start_y..end_y + 1 {
+			let row = y * self.width;
+			for cell in &mut self.cells[row + start_x..row + end_x + 1] {
+				*cell = op(*cell);
+			}
+		}
+	}
+}
+
+#[test]
+fn brightness_grid_test() {
+	let mut grid = BrightnessGrid::new(10, 10);
+
+	grid.brighten((0, 0), (9, 9), 1);
+	grid.brighten((0, 0), (9, 0), 2);
+	grid.dim((0, 0), (1, 1), 1);
+	grid.dim((0, 0), (0, 0), 5);
+
+	assert_eq!(0, grid.get(0, 0));
+	assert_eq!(2, grid.get(1, 0));
+	assert_eq!(0, grid.get(1, 1));
+	assert_eq!(3, grid.get(2, 0));
+	assert_eq!(100 + 20 - 4 - 2, grid.total());
+
+	grid.set((0, 9), (9, 9), 7);
+	assert_eq!(7, grid.get(4, 9));
+	assert_eq!(100 + 20 - 4 - 2 + 60, grid.total());
+}
+
+#[test]
+fn brightness_saturation_test() {
+	let mut grid = BrightnessGrid::new(2, 1);
+	grid.brighten((0, 0), (0, 0), u32::MAX);
+	grid.brighten((0, 0), (0, 0), u32::MAX);
+	grid.set((1, 0), (1, 0), u32::MAX);
+	grid.brighten((1, 0), (1, 0), 2);
+	assert_eq!((u32::MAX, u32::MAX), (grid.get(0, 0), grid.get(1, 0)));
+
+	let toggle = Effect::of(&Instructions::Toggle(::region::Region::Rectangle((0, 0), (0, 0))), Mode::Brightness);
+	assert_eq!(u32::MAX, toggle.apply(Effect::Set(u32::MAX).apply(0)));
+	assert_eq!(u32::MAX, Effect::Brighten(u32::MAX).apply(Effect::Brighten(u32::MAX).apply(0)));
+}
+
+/// Effect is what an instruction does to each light it covers, once the mode is taken into account.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Effect {
+	On,
+	Off,
+	Toggle,
+	Set(u32),
+	Brighten(u32),
+	Dim(u32),
+	Nothing,
+}
+
+impl Effect {
+	/// Returns the effect of `instruction` when run in `mode`.
+	pub fn of(instruction: &Instructions, mode: Mode) -> Effect {
+		match (mode, *instruction) {
+			(Mode::OnOff, Instructions::TurnOn(..)) => Effect::On,
+			(Mode::OnOff, Instructions::TurnOff(..)) => Effect::Off,
+			(Mode::OnOff, Instructions::Toggle(..)) => Effect::Toggle,
+			(Mode::OnOff, Instructions::Set(n, _)) => if n > 0 { Effect::On } else { Effect::Off },
+			(Mode::OnOff, Instructions::Brighten(n, _)) => if n > 0 { Effect::On } else { Effect::Nothing },
+			(Mode::OnOff, Instructions::Dim(n, _)) => if n > 0 { Effect::Off } else { Effect::Nothing },
+			(Mode::Brightness, Instructions::TurnOn(..)) => Effect::Brighten(1),
+			(Mode::Brightness, Instructions::TurnOff(..)) => Effect::Dim(1),
+			(Mode::Brightness, Instructions::Toggle(..)) => Effect::Brighten(2),
+			(Mode::Brightness, Instructions::Set(n, _)) => Effect::Set(n),
+			(Mode::Brightness, Instructions::Brighten(n, _)) => Effect::Brighten(n),
+			(Mode::Brightness, Instructions::Dim(n, _)) => Effect::Dim(n),
+		}
+	}
+
+	/// Returns the new value of a light, see `Grid::get`.
+	pub fn apply(&self, value: u32) -> u32 {
+		match *self {
+			Effect::On => 1,
+			Effect::Off => 0,
+			Effect::Toggle => value ^ 1,
+			Effect::Set(n) => n,
+			Effect::Brighten(n) => value.saturating_add(n),
+			Effect::Dim(n) => value.saturating_sub(n),
+			Effect::Nothing => value,
+		}
+	}
+}
+
+/// A Grid is a grid of lights in either mode.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Grid {
+	OnOff(BitGrid),
+	Brightness(BrightnessGrid),
+}
+
+impl Grid {
+	/// Creates a grid of `width` by `height` lights, all off.
+	pub fn new(mode: Mode, width: usize, height: usize) -> Grid {
+		match mode {
+			Mode::OnOff => Grid::OnOff(BitGrid::new(width, height)),
+			Mode::Brightness => Grid::Brightness(BrightnessGrid::new(width, height)),
+		}
+	}
+
+	pub fn width(&self) -> usize {
+		match *self {
+			Grid::OnOff(ref grid) => grid.width,
+			Grid::Brightness(ref grid) => grid.width,
+		}
+	}
+
+	pub fn height(&self) -> usize {
+		match *self {
+			Grid::OnOff(ref grid) => grid.height,
+			Grid::Brightness(ref grid) => grid.height,
+		}
+	}
+
+	/// Returns the value of the light at `(x, y)`: 1 or 0 in on/off mode, or its brightness.
+	pub fn get(&self, x: usize, y: usize) -> u32 {
+		match *self {
+			Grid::OnOff(ref grid) => grid.get(x, y) as u32,
+			Grid::Brightness(ref grid) => grid.get(x, y),
+		}
+	}
+
+	/// Returns the largest value of any light, see `get`.
+	pub fn max(&self) -> u32 {
+		match *self {
+			Grid::OnOff(ref grid) => if grid.count() > 0 { 1 } else { 0 },
+			Grid::Brightness(ref grid) => grid.max(),
+		}
+	}
+
+	/// Returns the mode the grid was created in.
+	pub fn mode(&self) -> Mode {
+		match *self {
+			Grid::OnOff(..) => Mode::OnOff,
+			Grid::Brightness(..) => Mode::Brightness,
+		}
+	}
+
+	/// Applies a single instruction to the grid.
+	pub fn apply(&mut self, instruction: &Instructions) {
+		let effect = Effect::of(instruction, self.mode());
+
+		for (start, end) in instruction.region().rectangles() {
+			self.apply_effect(effect, start, end);
+		}
+	}
+
+	/// Applies an effect to every light in the inclusive rectangle from `start` to `end`. Effects
+	/// that belong to the other mode are ignored.
+	pub fn apply_effect(&mut self, effect: Effect, start: Coordinate, end: Coordinate) {
+		match (self, effect) {
+			(&mut Grid::OnOff(ref mut grid), Effect::On) => grid.turn_on(start, end),
+			(&mut Grid::OnOff(ref mut grid), Effect::Off) => grid.turn_off(start, end),
+			(&mut Grid::OnOff(ref mut grid), Effect::Toggle) => grid.toggle(start, end),
+			(&mut Grid::Brightness(ref mut grid), Effect::Set(n)) => grid.set(start, end, n),
+			(&mut Grid::Brightness(ref mut grid), Effect::Brighten(n)) => grid.brighten(start, end, n),
+			(&mut Grid::Brightness(ref mut grid), Effect::Dim(n)) => grid.dim(start, end, n),
+			_ => {},
+		}
+	}
+
+	/// Joins grids of the same mode and width into one, the first grid at the top.
+	pub fn stack(grids: Vec<Grid>) -> Grid {
+		let mut grids = grids.into_iter();
+		let mut stacked = grids.next().expect("no grids to stack");
+
+		for grid in grids {
+			assert_eq!(stacked.width(), grid.width(), "stacked grids must have the same width");
+
+			match (&mut stacked, grid) {
+				(&mut Grid::OnOff(ref mut top), Grid::OnOff(bottom)) => {
+					top.height += bottom.height;
+					top.words.extend(bottom.words);
+				},
+				(&mut Grid::Brightness(ref mut top), Grid::Brightness(bottom)) => {
+					top.height += bottom.height;
+					top.cells.extend(bottom.cells);
+				},
+				_ => panic!("stacked grids must have the same mode"),
+			}
+		}
+
+		stacked
+	}
+
+	/// Returns the number of lights lit in on/off mode, or the total brightness in brightness mode.
+	pub fn total(&self) -> u64 {
+		match *self {
+			Grid::OnOff(ref grid) => grid.count(),
+			Grid::Brightness(ref grid) => grid.total(),
+		}
+	}
+}
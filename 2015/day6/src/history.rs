@@ -0,0 +1,325 @@
+//! Provides queries over the lights, and over the lights as they were part way through a program.
+//!
+//! A Summary holds the prefix sums of a grid, so that the lit count or brightness of any rectangle
+//! is found from four lookups rather than by visiting every light inside it.
+//!
+//! A History steps forward and backward through a program. Going forward applies the next
+//! instructions to the current grid. Going backward restores the nearest copy of the grid saved at
+//! a checkpoint, taken every `CHECKPOINT_INTERVAL` instructions, and replays the few instructions
+//! from there, so no step needs the program run again from the start.
+//!
+//! `explore` drives a History from commands typed one per line:
+//!
+//! * `next [n]` and `back [n]` run or undo one or `n` instructions.
+//! * `goto <k>` moves to the grid after the first `k` instructions.
+//! * `light x,y` shows a single light.
+//! * `total [x,y through x,y]` shows the lit count or brightness of the grid, or of a rectangle.
+
+use std::io;
+use std::io::prelude::*;
+use std::str::FromStr;
+
+use grid::{Grid, Mode};
+use {Coordinate, Instructions};
+
+/// The number of instructions between saved copies of the grid.
+pub const CHECKPOINT_INTERVAL: usize = 32;
+
+/// A Summary answers questions about rectangles of a grid as it was when the summary was made.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Summary {
+	width: usize,
+	height: usize,
+	/// `sums[y * (width + 1) + x]` is the total of the lights above and to the left of `(x, y)`.
+	sums: Vec<u64>,
+}
+
+impl Summary {
+	pub fn new(grid: &Grid) -> Summary {
+		let (width, height) = (grid.width(), grid.height());
+		let stride = width + 1;
+
+		let mut sums = vec!(0; stride * (height + 1));
+		for y in 0..height {
+			let mut row = 0;
+			for x in 0..width {
+				row += grid.get(x, y) as u64;
+				sums[(y + 1) * stride + x + 1] = sums[y * stride + x + 1] + row;
+			}
+		}
+
+		Summary { width, height, sums }
+	}
+
+	/// Returns the number of lights lit in on/off mode, or the total brightness in brightness
+	/// mode, of the inclusive rectangle from `start` to `end`.
+	///
+	/// Fails if the rectangle reaches outside of the grid.
+	pub fn total(&self, start: Coordinate, end: Coordinate) -> Result<u64, String> {
+		if start.0.min(end.0) < 0 || start.1.min(end.1) < 0 ||
+			start.0.max(end.0) >= self.width as i64 || start.1.max(end.1) >= self.height as i64 {
+
+			return Err(format!("{},{} through {},{} is outside of the {}x{} grid",
+				start.0, start.1, end.0, end.1, self.width, self.height));
+		}
+
+		let (x1, x2) = (start.0.min(end.0) as usize, start.0.max(end.0) as usize + 1);
+		let (y1, y2) = (start.1.min(end.1) as usize, start.1.max(end.1) as usize + 1);
+
+		let stride = self.width + 1;
+		let sum = |x: usize, y: usize| self.sums[y * stride + x];
+		Ok(sum(x2, y2) + sum(x1, y1) - sum(x1, y2) - sum(x2, y1))
+	}
+}
+
+#[test]
+fn summary_test() {
+	use region::Region;
+
+	let mut grid = Grid::new(Mode::Brightness, 5, 4);
+	grid.apply(&Instructions::TurnOn(Region::Rectangle((0, 0), (4, 3))));
+	grid.apply(&Instructions::Brighten(5, Region::Rectangle((1, 1), (2, 2))));
+
+	let summary = Summary::new(&grid);
+	assert_eq!(Ok(20 + 20), summary.total((0, 0), (4, 3)));
+	assert_eq!(Ok(6), summary.total((1, 1), (1, 1)));
+	assert_eq!(Ok(8 + 20), summary.total((2, 3), (1, 0)));
+	assert_eq!(Ok(1), summary.total((4, 3), (4, 3)));
+	assert_eq!(Err("0,0 through 5,0 is outside of the 5x4 grid".to_string()), summary.total((0, 0), (5, 0)));
+	assert!(summary.total((-1, 0), (2, 2)).is_err());
+}
+
+/// A History is a program together with the grid after some number of its instructions.
+pub struct History {
+	instructions: Vec<Instructions>,
+	grid: Grid,
+	/// The number of instructions that have been applied to `grid`.
+	step: usize,
+	/// `checkpoints[i]` is the grid after `i * CHECKPOINT_INTERVAL` instructions, saved the first
+	/// time the history passes that point.
+	checkpoints: Vec<Grid>,
+	summary: Option<Summary>,
+}
+
+impl History {
+	/// Creates a history of the instructions on a `width` by `height` grid, starting before the
+	/// first instruction.
+	///
+	/// Fails if an instruction lies outside of the grid.
+	pub fn new(instructions: &[Instructions], mode: Mode, width: usize, height: usize) -> Result<History, String> {
+		let instructions = ::validate_program(instructions, width, height)?;
+		let grid = Grid::new(mode, width, height);
+
+		Ok(History {
+			instructions,
+			checkpoints: vec!(grid.clone()),
+			grid,
+			step: 0,
+			summary: None,
+		})
+	}
+
+	/// Returns the number of instructions run so far.
+	pub fn step(&self) -> usize {
+		self.step
+	}
+
+	/// Returns the number of instructions in the program.
+	pub fn len(&self) -> usize {
+		self.instructions.len()
+	}
+
+	/// Returns the value of the light at `(x, y)`, see `Grid::get`.
+	pub fn get(&self, x: usize, y: usize) -> u32 {
+		self.grid.get(x, y)
+	}
+
+	/// Returns the number of lights lit, or the total brightness, of the whole grid.
+	pub fn total(&self) -> u64 {
+		self.grid.total()
+	}
+
+	/// Returns a summary of the grid after the instructions run so far, which is kept until the
+	/// history moves.
+	pub fn summary(&mut self) -> &Summary {
+		if self.summary.is_none() {
+			self.summary = Some(Summary::new(&self.grid));
+		}
+		self.summary.as_ref().unwrap()
+	}
+
+	/// Runs the next instruction, returning false if the program has already finished.
+	pub fn forward(&mut self) -> bool {
+		if self.step == self.instructions.len() {
+			return false;
+		}
+
+		self.grid.apply(&self.instructions[self.step]);
+		self.step += 1;
+		self.summary = None;
+
+		if self.step.is_multiple_of(CHECKPOINT_INTERVAL) && self.checkpoints.len() == self.step / CHECKPOINT_INTERVAL {
+			self.checkpoints.push(self.grid.clone());
+		}
+
+		true
+	}
+
+	/// Moves to the grid after the first `step` instructions, or after the whole program if it is
+	/// shorter than that.
+	pub fn seek(&mut self, step: usize) {
+		let step = step.min(self.instructions.len());
+
+		if step < self.step {
+			let checkpoint = step / CHECKPOINT_INTERVAL;
+			self.grid = self.checkpoints[checkpoint].clone();
+			self.step = checkpoint * CHECKPOINT_INTERVAL;
+			self.summary = None;
+		}
+
+		while self.step < step {
+			self.forward();
+		}
+	}
+}
+
+#[test]
+fn history_test() {
+	use compressed::random_instructions;
+
+	let instructions = random_instructions(100, 40, 3);
+	for &mode in &[Mode::OnOff, Mode::Brightness] {
+		let mut history = History::new(&instructions, mode, 40, 40).unwrap();
+		let mut totals = vec!(history.total());
+		while history.forward() {
+			totals.push(history.total());
+		}
+		assert_eq!(101, totals.len());
+		assert_eq!(100, history.step());
+		assert_eq!(4, history.checkpoints.len());
+
+		let mut grid = Grid::new(mode, 40, 40);
+		for instruction in &instructions[..70] {
+			grid.apply(instruction);
+		}
+
+		while history.step() > 70 {
+			let step = history.step() - 1;
+			history.seek(step);
+		}
+		assert_eq!(grid, history.grid);
+		assert_eq!(grid.get(12, 34), history.get(12, 34));
+		assert_eq!(Ok(grid.total()), history.summary().total((0, 0), (39, 39)));
+
+		for &step in &[5, 64, 63, 0, 100, 31, 250] {
+			history.seek(step);
+			assert_eq!(step.min(100), history.step());
+			assert_eq!(totals[step.min(100)], history.total());
+			assert_eq!(Ok(totals[step.min(100)]), history.summary().total((39, 0), (0, 39)));
+		}
+		assert!(!history.forward());
+	}
+
+	assert!(History::new(&instructions, Mode::OnOff, 39, 40).is_err());
+}
+
+/// Reads commands from `input` and writes the answers to `output` until the input runs out or a
+/// `quit` command is read. See the module documentation for the commands.
+pub fn explore<R: BufRead, W: Write>(history: &mut History, input: R, output: &mut W) -> io::Result<()> {
+	for line in input.lines() {
+		let line = line?;
+		let words: Vec<&str> = line.split_whitespace().collect();
+
+		let answer = match words.split_first() {
+			None => continue,
+			Some((&"quit", _)) => break,
+			Some((&command, arguments)) => run_command(history, command, &arguments.join(" ")),
+		};
+
+		match answer {
+			Ok(answer) => writeln!(output, "{}", answer)?,
+			Err(e) => writeln!(output, "{}", e)?,
+		}
+	}
+
+	Ok(())
+}
+
+fn run_command(history: &mut History, command: &str, arguments: &str) -> Result<String, String> {
+	let count = || if arguments.is_empty() {
+		Ok(1)
+	} else {
+		usize::from_str(arguments).map_err(|_| format!("expected a number of instructions, not {:?}", arguments))
+	};
+
+	match command {
+		"next" => {
+			let step = history.step().saturating_add(count()?).min(history.len());
+			history.seek(step);
+		},
+		"back" => {
+			let step = history.step().saturating_sub(count()?);
+			history.seek(step);
+		},
+		"goto" => history.seek(count()?),
+		"light" => {
+			let (x, y) = parse_coordinate(arguments)?;
+			if x < 0 || y < 0 || x as usize >= history.grid.width() || y as usize >= history.grid.height() {
+				return Err(format!("{},{} is outside of the grid", x, y));
+			}
+			return Ok(format!("The light at {},{} is {}", x, y, history.get(x as usize, y as usize)));
+		},
+		"total" if arguments.is_empty() => {},
+		"total" => {
+			let (start, end) = match arguments.split_once("through") {
+				Some((start, end)) => (parse_coordinate(start)?, parse_coordinate(end)?),
+				None => return Err(format!("expected x,y through x,y, not {:?}", arguments)),
+			};
+			let total = history.summary().total(start, end)?;
+			return Ok(format!("{} from {},{} through {},{}", total, start.0, start.1, end.0, end.1));
+		},
+		_ => return Err(format!("unknown command {:?}, expected next, back, goto, light, total or quit", command)),
+	}
+
+	Ok(format!("{} after {} of {} instructions", history.total(), history.step(), history.len()))
+}
+
+fn parse_coordinate(text: &str) -> Result<Coordinate, String> {
+	let coordinate = text.trim().split_once(',')
+		.and_then(|(x, y)| i64::from_str(x.trim()).ok().zip(i64::from_str(y.trim()).ok()));
+
+	coordinate.ok_or(format!("expected x,y, not {:?}", text.trim()))
+}
+
+#[test]
+fn explore_test() {
+	use region::Region;
+
+	let instructions = vec!(
+		Instructions::TurnOn(Region::Rectangle((0, 0), (3, 3))),
+		Instructions::Toggle(Region::Rectangle((0, 0), (1, 1))),
+		Instructions::TurnOff(Region::Point((3, 3))),
+	);
+	let mut history = History::new(&instructions, Mode::OnOff, 4, 4).unwrap();
+
+	let input = "next\nlight 0,0\n\nnext 5\ntotal 0,0 through 1,3\nback 2\ngoto 2\nlight 9,0\n\
+		total 0,0\nfly\nquit\nnext\n";
+	let mut output = Vec::new();
+	explore(&mut history, input.as_bytes(), &mut output).unwrap();
+
+	assert_eq!("16 after 1 of 3 instructions\n\
+		The light at 0,0 is 1\n\
+		11 after 3 of 3 instructions\n\
+		4 from 0,0 through 1,3\n\
+		16 after 1 of 3 instructions\n\
+		12 after 2 of 3 instructions\n\
+		9,0 is outside of the grid\n\
+		expected x,y through x,y, not \"0,0\"\n\
+		unknown command \"fly\", expected next, back, goto, light, total or quit\n",
+		String::from_utf8(output).unwrap());
+
+	// Moving further than the program goes stops at either end.
+	let max = usize::MAX.to_string();
+	assert_eq!(Ok("11 after 3 of 3 instructions".to_string()), run_command(&mut history, "next", &max));
+	assert_eq!(Ok("0 after 0 of 3 instructions".to_string()), run_command(&mut history, "back", &max));
+}
@@ -0,0 +1,274 @@
+//! Provides rendering of a light grid to an image file.
+//!
+//! Images are written as binary PGM (grayscale), binary PPM (colour) or PNG. All three are simple
+//! enough to write by hand: the PNG encoder stores the pixels uncompressed, which makes for larger
+//! files but needs nothing beyond a couple of checksums.
+
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+
+use grid::Grid;
+
+/// Format is one of the supported image file formats.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Format {
+	Pgm,
+	Ppm,
+	Png,
+}
+
+impl Format {
+	/// Picks the format matching the extension of `path`.
+	pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Format, String> {
+		let extension = path.as_ref().extension()
+			.and_then(|e| e.to_str())
+			.map(|e| e.to_lowercase());
+
+		match extension.as_deref() {
+			Some("pgm") => Ok(Format::Pgm),
+			Some("ppm") => Ok(Format::Ppm),
+			Some("png") => Ok(Format::Png),
+			_ => Err(format!("{} should end in .pgm, .ppm or .png", path.as_ref().display())),
+		}
+	}
+}
+
+/// An Image is an 8 bit grayscale picture.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+	pub width: usize,
+	pub height: usize,
+	/// One byte per pixel, row by row from the top left.
+	pub pixels: Vec<u8>,
+}
+
+impl Image {
+	/// Renders a grid with one pixel per light.
+	///
+	/// In on/off mode lit lights are white and the rest black. In brightness mode each light is a
+	/// shade of gray, scaled so that the brightest light is white.
+	pub fn render(grid: &Grid) -> Image {
+		let (width, height) = (grid.width(), grid.height());
+		let max = grid.max();
+
+		let mut pixels = Vec::with_capacity(width * height);
+		for y in 0..height {
+			for x in 0..width {
+				pixels.push(scale(grid.get(x, y), max));
+			}
+		}
+
+		Image { width, height, pixels }
+	}
+
+	/// Shrinks the image by `factor` in both directions, each pixel becoming the average of the
+	/// block of pixels it replaces. Blocks at the right and bottom edges may be partial.
+	pub fn downscale(&self, factor: usize) -> Image {
+		if factor <= 1 {
+			return self.clone();
+		}
+
+		let width = self.width.div_ceil(factor);
+		let height = self.height.div_ceil(factor);
+
+		let mut sums = vec!(0u64; width * height);
+		let mut counts = vec!(0u64; width * height);
+		for y in 0..self.height {
+			for x in 0..self.width {
+				let i = (y / factor) * width + x / factor;
+				sums[i] += self.pixels[y * self.width + x] as u64;
+				counts[i] += 1;
+			}
+		}
+
+		let pixels = sums.iter().zip(counts.iter()).map(|(&sum, &count)| (sum / count) as u8).collect();
+
+		Image { width, height, pixels }
+	}
+
+	/// Writes the image to `w` in the given format.
+	pub fn write<W: Write>(&self, format: Format, w: &mut W) -> io::Result<()> {
+		match format {
+			Format::Pgm => {
+				write!(w, "P5\n{} {}\n255\n", self.width, self.height)?;
+				w.write_all(&self.pixels)
+			},
+			Format::Ppm => {
+				write!(w, "P6\n{} {}\n255\n", self.width, self.height)?;
+				let rgb: Vec<u8> = self.pixels.iter().flat_map(|&p| vec!(p, p, p)).collect();
+				w.write_all(&rgb)
+			},
+			Format::Png => write_png(self, w),
+		}
+	}
+
+	/// Writes the image to `path`, in the format matching its extension.
+	pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+		let format = Format::from_path(&path)?;
+		let name = path.as_ref().display();
+
+		let mut f = File::create(&path).map_err(|e| format!("cannot create {}: {}", name, e))?;
+		let mut w = io::BufWriter::new(&mut f);
+		self.write(format, &mut w)
+			.and_then(|_| w.flush())
+			.map_err(|e| format!("cannot write {}: {}", name, e))
+	}
+}
+
+/// Maps a light's value onto a gray level, with `max` as white.
+fn scale(value: u32, max: u32) -> u8 {
+	if max == 0 {
+		0
+	} else {
+		(value as u64 * 255 / max as u64) as u8
+	}
+}
+
+#[test]
+fn render_test() {
+	use grid::Mode;
+	use region::Region;
+	use Instructions;
+
+	let mut grid = Grid::new(Mode::OnOff, 3, 2);
+	grid.apply(&Instructions::TurnOn(Region::Rectangle((1, 0), (2, 1))));
+	assert_eq!(vec!(0, 255, 255, 0, 255, 255), Image::render(&grid).pixels);
+
+	let mut grid = Grid::new(Mode::Brightness, 3, 2);
+	grid.apply(&Instructions::TurnOn(Region::Rectangle((1, 0), (2, 1))));
+	grid.apply(&Instructions::Toggle(Region::Point((2, 0))));
+	assert_eq!(vec!(0, 85, 255, 0, 85, 85), Image::render(&grid).pixels);
+
+	let grid = Grid::new(Mode::Brightness, 2, 1);
+	assert_eq!(vec!(0, 0), Image::render(&grid).pixels);
+}
+
+#[test]
+fn downscale_test() {
+	let image = Image { width: 3, height: 3, pixels: vec!(0, 255, 255, 255, 255, 255, 9, 9, 9) };
+
+	assert_eq!(image, image.downscale(1));
+	assert_eq!(Image { width: 2, height: 2, pixels: vec!(191, 255, 9, 9) }, image.downscale(2));
+	assert_eq!(Image { width: 1, height: 1, pixels: vec!(144) }, image.downscale(3));
+}
+
+#[test]
+fn write_test() {
+	let image = Image { width: 2, height: 1, pixels: vec!(0, 255) };
+
+	let mut pgm = Vec::new();
+	image.write(Format::Pgm, &mut pgm).unwrap();
+	assert_eq!(b"P5\n2 1\n255\n\x00\xff".to_vec(), pgm);
+
+	let mut ppm = Vec::new();
+	image.write(Format::Ppm, &mut ppm).unwrap();
+	assert_eq!(b"P6\n2 1\n255\n\x00\x00\x00\xff\xff\xff".to_vec(), ppm);
+
+	let mut png = Vec::new();
+	image.write(Format::Png, &mut png).unwrap();
+	assert_eq!(b"\x89PNG\r\n\x1a\n".to_vec(), png[..8].to_vec());
+	assert_eq!(b"IHDR".to_vec(), png[12..16].to_vec());
+	assert_eq!(b"IEND".to_vec(), png[png.len() - 8..png.len() - 4].to_vec());
+}
+
+/// The largest block that the deflate format can store uncompressed.
+const MAX_STORED_BLOCK: usize = 65535;
+
+/// Writes the image as an 8 bit grayscale PNG.
+fn write_png<W: Write>(image: &Image, w: &mut W) -> io::Result<()> {
+	w.write_all(b"\x89PNG\r\n\x1a\n")?;
+
+	let mut header = Vec::new();
+	header.extend_from_slice(&(image.width as u32).to_be_bytes());
+	header.extend_from_slice(&(image.height as u32).to_be_bytes());
+	// 8 bits per pixel, grayscale, deflate, standard filters, no interlacing.
+	header.extend_from_slice(&[8, 0, 0, 0, 0]);
+	write_chunk(w, b"IHDR", &header)?;
+
+	// Each row is preceded by its filter type, which is always 0 (none).
+	let mut raw = Vec::with_capacity((image.width + 1) * image.height);
+	for row in image.pixels.chunks(image.width.max(1)) {
+		raw.push(0);
+		raw.extend_from_slice(row);
+	}
+
+	// A zlib stream holding the rows in stored (uncompressed) deflate blocks.
+	let mut data = vec!(0x78, 0x01);
+	let blocks: Vec<&[u8]> = raw.chunks(MAX_STORED_BLOCK).collect();
+	for (i, block) in blocks.iter().enumerate() {
+		let last = if i + 1 == blocks.len() { 1 } else { 0 };
+		let len = block.len() as u16;
+		data.push(last);
+		data.extend_from_slice(&len.to_le_bytes());
+		data.extend_from_slice(&(!len).to_le_bytes());
+		data.extend_from_slice(block);
+	}
+	data.extend_from_slice(&adler32(&raw).to_be_bytes());
+	write_chunk(w, b"IDAT", &data)?;
+
+	write_chunk(w, b"IEND", &[])
+}
+
+/// Writes a PNG chunk: its length, type, data and the checksum of the type and data.
+fn write_chunk<W: Write>(w: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+	w.write_all(&(data.len() as u32).to_be_bytes())?;
+	w.write_all(kind)?;
+	w.write_all(data)?;
+
+	let mut crc = Crc32::new();
+	crc.update(kind);
+	crc.update(data);
+	w.write_all(&crc.finish().to_be_bytes())
+}
+
+/// Crc32 computes the CRC-32 checksum used by PNG chunks.
+struct Crc32 {
+	table: [u32; 256],
+	crc: u32,
+}
+
+impl Crc32 {
+	fn new() -> Crc32 {
+		let mut table = [0u32; 256];
+		for (n, entry) in table.iter_mut().enumerate() {
+			let mut c = n as u32;
+			for _ in 0..8 {
+				c = if c & 1 != 0 { 0xedb88320 ^ (c >> 1) } else { c >> 1 };
+			}
+			*entry = c;
+		}
+
+		Crc32 { table, crc: 0xffffffff }
+	}
+
+	fn update(&mut self, bytes: &[u8]) {
+		for &byte in bytes {
+			self.crc = self.table[((self.crc ^ byte as u32) & 0xff) as usize] ^ (self.crc >> 8);
+		}
+	}
+
+	fn finish(&self) -> u32 {
+		self.crc ^ 0xffffffff
+	}
+}
+
+/// Computes the Adler-32 checksum that ends a zlib stream.
+fn adler32(bytes: &[u8]) -> u32 {
+	let (mut a, mut b) = (1u32, 0u32);
+	for &byte in bytes {
+		a = (a + byte as u32) % 65521;
+		b = (b + a) % 65521;
+	}
+	(b << 16) | a
+}
+
+#[test]
+fn checksum_test() {
+	let mut crc = Crc32::new();
+	crc.update(b"IEND");
+	assert_eq!(0xae426082, crc.finish());
+
+	assert_eq!(0x11e60398, adler32(b"Wikipedia"));
+}
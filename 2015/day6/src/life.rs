@@ -0,0 +1,402 @@
+//! Provides Game of Life style animation of the lights, as in "Like a GIF For Your Yard".
+//!
+//! Every step, each light looks at its neighbours and decides whether it is on in the next step: a
+//! light that is off turns on if its number of lit neighbours is one of the rule's birth counts, and
+//! a light that is on stays on if its number of lit neighbours is one of the survival counts. Rules
+//! are written in the usual `B3/S23` notation, which is Conway's Game of Life.
+//!
+//! The neighbours are either the eight lights around a light (Moore) or only the four that share
+//! an edge with it (von Neumann). At the edges of the grid the missing neighbours are either
+//! counted as off (bounded) or taken from the opposite edge (toroidal). Stuck lights are always on,
+//! whatever the rule says.
+
+use std::fmt;
+use std::str::FromStr;
+
+use grid::{BitGrid, Grid, Mode};
+
+/// Neighbourhood is the set of lights counted as neighbours of a light.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Neighbourhood {
+	/// The eight lights around a light.
+	Moore,
+	/// The four lights above, below, left and right of a light.
+	VonNeumann,
+}
+
+impl Neighbourhood {
+	fn offsets(&self) -> &'static [(isize, isize)] {
+		match *self {
+			Neighbourhood::Moore => &[(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)],
+			Neighbourhood::VonNeumann => &[(0, -1), (-1, 0), (1, 0), (0, 1)],
+		}
+	}
+}
+
+impl FromStr for Neighbourhood {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Neighbourhood, String> {
+		match s {
+			"moore" => Ok(Neighbourhood::Moore),
+			"von-neumann" => Ok(Neighbourhood::VonNeumann),
+			_ => Err(format!("unknown neighbourhood {:?}, expected moore or von-neumann", s)),
+		}
+	}
+}
+
+/// Edges decides what lies beyond the edges of the grid.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Edges {
+	/// Lights beyond the edges are always off.
+	Bounded,
+	/// The grid wraps around, so the lights beyond an edge are those at the opposite edge.
+	Toroidal,
+}
+
+impl FromStr for Edges {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Edges, String> {
+		match s {
+			"bounded" => Ok(Edges::Bounded),
+			"toroidal" => Ok(Edges::Toroidal),
+			_ => Err(format!("unknown edges {:?}, expected bounded or toroidal", s)),
+		}
+	}
+}
+
+/// A Rule holds the neighbour counts that turn a light on and keep it on, one bit per count.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Rule {
+	birth: u16,
+	survival: u16,
+}
+
+impl FromStr for Rule {
+	type Err = String;
+
+	/// Parses a rule such as `B3/S23`. Either list of counts may be empty, as in `B2/S`.
+	fn from_str(s: &str) -> Result<Rule, String> {
+		let counts = |part: &str, prefix: char| -> Result<u16, String> {
+			let digits = part.strip_prefix(prefix)
+				.or_else(|| part.strip_prefix(prefix.to_ascii_lowercase()))
+				.ok_or(format!("rule {:?} should look like B3/S23", s))?;
+
+			digits.chars().try_fold(0, |counts, digit| match digit.to_digit(10) {
+				Some(count) if count <= 8 => Ok(counts | 1 << count),
+				_ => Err(format!("rule {:?} has a neighbour count {:?} outside of 0 to 8", s, digit)),
+			})
+		};
+
+		match s.split_once('/') {
+			Some((birth, survival)) => Ok(Rule { birth: counts(birth, 'B')?, survival: counts(survival, 'S')? }),
+			None => Err(format!("rule {:?} should look like B3/S23", s)),
+		}
+	}
+}
+
+impl fmt::Display for Rule {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let digits = |counts: u16| (0..9).filter(|count| counts & 1 << count != 0).map(|count| count.to_string()).collect::<String>();
+		write!(f, "B{}/S{}", digits(self.birth), digits(self.survival))
+	}
+}
+
+/// Life steps a grid of lights forward under a rule.
+pub struct Life {
+	grid: BitGrid,
+	rule: Rule,
+	neighbourhood: Neighbourhood,
+	edges: Edges,
+	stuck: Vec<(usize, usize)>,
+}
+
+impl Life {
+	/// Starts from `grid`, turning on the stuck lights straight away.
+	///
+	/// Fails if a stuck light lies outside of the grid.
+	pub fn new(grid: BitGrid, rule: Rule, neighbourhood: Neighbourhood, edges: Edges, stuck: Vec<(usize, usize)>)
+		-> Result<Life, String> {
+
+		if let Some(&(x, y)) = stuck.iter().find(|&&(x, y)| x >= grid.width() || y >= grid.height()) {
+			return Err(format!("the stuck light {},{} is outside of the {}x{} grid", x, y, grid.width(), grid.height()));
+		}
+
+		let mut life = Life { grid, rule, neighbourhood, edges, stuck };
+		life.stick();
+		Ok(life)
+	}
+
+	/// Returns the current grid.
+	pub fn grid(&self) -> &BitGrid {
+		&self.grid
+	}
+
+	/// Returns the number of lights that are on.
+	pub fn population(&self) -> u64 {
+		self.grid.count()
+	}
+
+	/// Moves every light forward by `steps` steps.
+	pub fn run(&mut self, steps: usize) {
+		for _ in 0..steps {
+			self.step();
+		}
+	}
+
+	/// Moves every light forward by a single step.
+	pub fn step(&mut self) {
+		let (width, height) = (self.grid.width(), self.grid.height());
+		let mut next = BitGrid::new(width, height);
+
+		for y in 0..height {
+			for x in 0..width {
+				let neighbours = self.neighbours(x, y);
+				let counts = if self.grid.get(x, y) { self.rule.survival } else { self.rule.birth };
+				if counts & 1 << neighbours != 0 {
+					next.set(x, y, true);
+				}
+			}
+		}
+
+		self.grid = next;
+		self.stick();
+	}
+
+	/// Counts the lit neighbours of the light at `(x, y)`.
+	fn neighbours(&self, x: usize, y: usize) -> u32 {
+		let (width, height) = (self.grid.width() as isize, self.grid.height() as isize);
+
+		self.neighbourhood.offsets().iter()
+			.filter(|&&(dx, dy)| {
+				let (mut nx, mut ny) = (x as isize + dx, y as isize + dy);
+				if self.edges == Edges::Toroidal {
+					nx = nx.rem_euclid(width);
+					ny = ny.rem_euclid(height);
+				}
+				nx >= 0 && ny >= 0 && nx < width && ny < height && self.grid.get(nx as usize, ny as usize)
+			})
+			.count() as u32
+	}
+
+	fn stick(&mut self) {
+		for &(x, y) in &self.stuck {
+			self.grid.set(x, y, true);
+		}
+	}
+}
+
+/// Options holds the settings given on the command line after `life`.
+///
+/// * `--picture <file>` starts from a picture of the lights, see `parse_picture`. Otherwise the
+///   lights start as `input.txt` leaves them in on/off mode, on a grid set by `--width <lights>`
+///   and `--height <lights>` (1000 by 1000 by default).
+/// * `--steps <count>` sets how many steps to run, 100 by default.
+/// * `--rule <rule>` sets the rule, `B3/S23` by default.
+/// * `--neighbourhood <moore|von-neumann>` and `--edges <bounded|toroidal>` default to `moore` and
+///   `bounded`.
+/// * `--stuck <x,y>` keeps a light on, and may be given more than once. `--stuck-corners` keeps the
+///   four corner lights on.
+/// * `--image <file.pgm|file.ppm|file.png>` saves a picture of the lights after the last step.
+struct Options {
+	picture: Option<String>,
+	width: usize,
+	height: usize,
+	steps: usize,
+	rule: Rule,
+	neighbourhood: Neighbourhood,
+	edges: Edges,
+	stuck: Vec<(usize, usize)>,
+	stuck_corners: bool,
+	image: Option<String>,
+}
+
+pub const USAGE: &str = "Usage: day6 life [--picture <file>] [--width <lights>] [--height <lights>] \
+	[--steps <count>] [--rule <B3/S23>] [--neighbourhood <moore|von-neumann>] [--edges <bounded|toroidal>] \
+	[--stuck <x,y>]... [--stuck-corners] [--image <file.pgm|file.ppm|file.png>]";
+
+impl Options {
+	fn parse(args: &[String]) -> Result<Options, String> {
+		let mut options = Options {
+			picture: None,
+			width: 1000,
+			height: 1000,
+			steps: 100,
+			rule: Rule { birth: 1 << 3, survival: 1 << 2 | 1 << 3 },
+			neighbourhood: Neighbourhood::Moore,
+			edges: Edges::Bounded,
+			stuck: Vec::new(),
+			stuck_corners: false,
+			image: None,
+		};
+
+		let mut args = args.iter();
+		while let Some(arg) = args.next() {
+			let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+
+			match arg.as_str() {
+				"--picture" => options.picture = Some(value()?.clone()),
+				"--width" => options.width = ::parse_size("--width", value()?)?,
+				"--height" => options.height = ::parse_size("--height", value()?)?,
+				"--steps" => {
+					let steps = value()?;
+					options.steps = usize::from_str(steps)
+						.map_err(|_| format!("--steps must be a whole number, not {:?}", steps))?;
+				},
+				"--rule" => options.rule = Rule::from_str(value()?)?,
+				"--neighbourhood" => options.neighbourhood = Neighbourhood::from_str(value()?)?,
+				"--edges" => options.edges = Edges::from_str(value()?)?,
+				"--stuck" => {
+					let light = value()?;
+					let coordinate = light.split_once(',')
+						.and_then(|(x, y)| usize::from_str(x).ok().zip(usize::from_str(y).ok()))
+						.ok_or(format!("--stuck expects x,y, not {:?}", light))?;
+					options.stuck.push(coordinate);
+				},
+				"--stuck-corners" => options.stuck_corners = true,
+				"--image" => {
+					let path = value()?;
+					::image::Format::from_path(path)?;
+					options.image = Some(path.clone());
+				},
+				_ => return Err(format!("unknown option {:?}", arg)),
+			}
+		}
+
+		::check_size(options.width, options.height)?;
+		Ok(options)
+	}
+}
+
+/// Runs the `life` command with the arguments that follow it.
+pub fn main(args: &[String]) -> Result<(), String> {
+	use std::fs;
+
+	let options = Options::parse(args)?;
+
+	let grid = match options.picture {
+		Some(ref path) => {
+			let input = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+			parse_picture(&input)?
+		},
+		None => {
+			let input = fs::read_to_string("input.txt").map_err(|e| format!("cannot read input.txt: {}", e))?;
+			match ::light_grid(&::parse_program(&input), Mode::OnOff, options.width, options.height)? {
+				Grid::OnOff(grid) => grid,
+				Grid::Brightness(..) => unreachable!(),
+			}
+		},
+	};
+
+	let mut stuck = options.stuck.clone();
+	if options.stuck_corners && grid.width() > 0 && grid.height() > 0 {
+		let (right, bottom) = (grid.width() - 1, grid.height() - 1);
+		stuck.extend_from_slice(&[(0, 0), (right, 0), (0, bottom), (right, bottom)]);
+	}
+
+	let mut life = Life::new(grid, options.rule, options.neighbourhood, options.edges, stuck)?;
+	println!("Starting {} with {} lights on", options.rule, life.population());
+	life.run(options.steps);
+	println!("After {} steps there are {} lights on", options.steps, life.population());
+
+	if let Some(ref path) = options.image {
+		::image::Image::render(&Grid::OnOff(life.grid().clone())).save(path)?;
+		println!("Saved a picture of the lights to {}", path);
+	}
+
+	Ok(())
+}
+
+/// Reads a picture of the lights, one row per line with `#` for on and `.` for off.
+pub fn parse_picture(input: &str) -> Result<BitGrid, String> {
+	let rows: Vec<&str> = input.lines().map(|line| line.trim_end()).filter(|line| !line.is_empty()).collect();
+	let width = rows.first().map_or(0, |row| row.len());
+
+	let mut grid = BitGrid::new(width, rows.len());
+	for (y, row) in rows.iter().enumerate() {
+		if row.len() != width {
+			return Err(format!("row {} is {} lights wide but the first row is {}", y + 1, row.len(), width));
+		}
+
+		for (x, light) in row.chars().enumerate() {
+			match light {
+				'#' => grid.set(x, y, true),
+				'.' => {},
+				_ => return Err(format!("row {} has {:?} where a light should be, expected # or .", y + 1, light)),
+			}
+		}
+	}
+
+	Ok(grid)
+}
+
+/// Draws the lights as `parse_picture` reads them.
+#[cfg(test)]
+fn picture(grid: &BitGrid) -> String {
+	(0..grid.height())
+		.map(|y| (0..grid.width()).map(|x| if grid.get(x, y) { '#' } else { '.' }).collect::<String>() + "\n")
+		.collect()
+}
+
+#[test]
+fn rule_test() {
+	let life = Rule::from_str("B3/S23").unwrap();
+	assert_eq!(Rule { birth: 1 << 3, survival: 1 << 2 | 1 << 3 }, life);
+	assert_eq!("B3/S23", life.to_string());
+	assert_eq!("B36/S", Rule::from_str("b63/s").unwrap().to_string());
+
+	assert!(Rule::from_str("B3S23").is_err());
+	assert!(Rule::from_str("S23/B3").is_err());
+	assert_eq!(Err("rule \"B9/S23\" has a neighbour count '9' outside of 0 to 8".to_string()), Rule::from_str("B9/S23"));
+}
+
+#[test]
+fn life_test() {
+	let start = ".#.#.#\n...##.\n#....#\n..#...\n#.#..#\n####..\n";
+	let conway = Rule::from_str("B3/S23").unwrap();
+
+	let mut life = Life::new(parse_picture(start).unwrap(), conway, Neighbourhood::Moore, Edges::Bounded, vec!()).unwrap();
+	assert_eq!(15, life.population());
+	life.step();
+	assert_eq!("..##..\n..##.#\n...##.\n......\n#.....\n#.##..\n", picture(life.grid()));
+	life.run(3);
+	assert_eq!(4, life.population());
+
+	let corners = vec!((0, 0), (5, 0), (0, 5), (5, 5));
+	let mut life = Life::new(parse_picture(start).unwrap(), conway, Neighbourhood::Moore, Edges::Bounded, corners).unwrap();
+	life.run(5);
+	assert_eq!("##.###\n.##..#\n.##...\n.##...\n#.#...\n##...#\n", picture(life.grid()));
+	assert_eq!(17, life.population());
+
+	// A glider travels one light diagonally every four steps, so it crosses a 5x5 torus in 20.
+	let glider = ".#...\n..#..\n###..\n.....\n.....\n";
+	let mut life = Life::new(parse_picture(glider).unwrap(), conway, Neighbourhood::Moore, Edges::Toroidal, vec!()).unwrap();
+	life.run(4);
+	assert_eq!(".....\n..#..\n...#.\n.###.\n.....\n", picture(life.grid()));
+	life.run(16);
+	assert_eq!(glider, picture(life.grid()));
+
+	// With only the four edge neighbours, B1/S swaps a single light for the four around it.
+	let dot = ".....\n.....\n..#..\n.....\n.....\n";
+	let rule = Rule::from_str("B1/S").unwrap();
+	let mut life = Life::new(parse_picture(dot).unwrap(), rule, Neighbourhood::VonNeumann, Edges::Bounded, vec!()).unwrap();
+	life.step();
+	assert_eq!(".....\n..#..\n.#.#.\n..#..\n.....\n", picture(life.grid()));
+
+	assert!(Life::new(parse_picture(dot).unwrap(), rule, Neighbourhood::Moore, Edges::Bounded, vec!((5, 0))).is_err());
+}
+
+#[test]
+fn options_test() {
+	let huge = format!("{}", usize::MAX / 2);
+	let args = vec!("--height".to_string(), huge.clone());
+	assert_eq!(Some(format!("a grid of 1000 by {} lights is too large", huge)), Options::parse(&args).err());
+}
+
+#[test]
+fn parse_picture_test() {
+	assert_eq!(".#\n#.\n", picture(&parse_picture(".#\n#.\n\n").unwrap()));
+	assert_eq!(Err("row 2 is 3 lights wide but the first row is 2".to_string()), parse_picture("..\n...\n"));
+	assert_eq!(Err("row 1 has 'o' where a light should be, expected # or .".to_string()), parse_picture(".o\n"));
+}
@@ -1,4 +1,5 @@
 #![feature(iter_arith)]
+#![feature(test)]
 
 /// # Day 6: Probably a Fire Hazard
 /// 
@@ -27,48 +28,468 @@
 /// * `turn off 499,499 through 500,500` would turn off (or leave off) the middle four lights.
 /// 
 /// After following the instructions, *how many lights are lit*?
+///
+/// ## Part Two
+///
+/// The light grid actually has individual brightness controls. `turn on` increases the brightness
+/// of the lights by `1`, `turn off` decreases it by `1` to a minimum of zero, and `toggle` increases
+/// it by `2`.
+///
+/// What is the *total brightness* of all lights combined after following Santa's instructions?
 
 extern crate regex;
+#[cfg(test)]
+extern crate test;
+
+mod animation;
+mod compressed;
+mod contribution;
+mod grid;
+mod history;
+mod image;
+mod life;
+mod parallel;
+mod plane;
+mod region;
+
+use grid::{Grid, Mode};
+use region::Region;
+
+/// Engine selects how the instructions are evaluated.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Engine {
+	/// Every light is stored and updated, see `light_grid`.
+	Grid,
+	/// Lights are grouped into blocks along the instruction edges, see `compressed::run_compressed`.
+	Compressed,
+	/// Lights are stored as runs on an unbounded plane, see `plane::Plane`.
+	Plane,
+}
+
+impl std::str::FromStr for Engine {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Engine, String> {
+		match s {
+			"grid" => Ok(Engine::Grid),
+			"compressed" => Ok(Engine::Compressed),
+			"plane" => Ok(Engine::Plane),
+			_ => Err(format!("unknown engine {:?}, expected grid, compressed or plane", s)),
+		}
+	}
+}
+
+/// Options holds the settings given on the command line.
+///
+/// * `--mode <on-off|brightness>` answers only one part of the puzzle instead of both.
+/// * `--engine <grid|compressed|plane>` chooses how the instructions are evaluated.
+/// * `--width <lights>` and `--height <lights>` set the size of the grid, 1000 by 1000 by default.
+///   The plane engine has no edges and ignores them.
+/// * `--image <file.pgm|file.ppm|file.png>` saves a picture of the final grid.
+/// * `--animate <file.gif|file.pgm|file.ppm|file.png>` saves a frame of the grid as the program
+///   runs, either into an animated GIF or as a numbered sequence of images.
+/// * `--every <instructions>` sets how many instructions are run between frames, 10 by default.
+/// * `--downscale <factor>` shrinks each frame by a factor, 1 by default.
+/// * `--contributions` reports how much each instruction adds to the answer, and which
+///   instructions could be left out without changing any light.
+/// * `--threads <count>` splits the grid engine's work between several threads, 1 by default.
+/// * `--explore` reads commands from standard input to step through the program and query the
+///   lights, see `history::explore`. It needs `--mode` to pick a single mode.
+///
+/// When both modes are run the mode is added to image file names, as in `lights-on-off.png`.
+///
+/// `day6 life` takes a different set of options, see `life::Options`.
+struct Options {
+	modes: Vec<Mode>,
+	engine: Engine,
+	width: usize,
+	height: usize,
+	image: Option<String>,
+	animation: Option<String>,
+	every: usize,
+	downscale: usize,
+	threads: usize,
+	explore: bool,
+	contributions: bool,
+}
+
+const USAGE: &str = "Usage: day6 [--mode <on-off|brightness>] [--engine <grid|compressed|plane>] \
+	[--width <lights>] [--height <lights>] [--image <file.pgm|file.ppm|file.png>] \
+	[--animate <file.gif|file.pgm|file.ppm|file.png>] [--every <instructions>] [--downscale <factor>] \
+	[--threads <count>] [--explore] [--contributions]";
+
+impl Options {
+	fn parse(args: &[String]) -> Result<Options, String> {
+		use std::str::FromStr;
+
+		let mut options = Options {
+			modes: vec!(Mode::OnOff, Mode::Brightness),
+			engine: Engine::Grid,
+			width: 1000,
+			height: 1000,
+			image: None,
+			animation: None,
+			every: 10,
+			downscale: 1,
+			threads: 1,
+			explore: false,
+			contributions: false,
+		};
+
+		let mut args = args.iter();
+		while let Some(arg) = args.next() {
+			let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+
+			match arg.as_str() {
+				"--mode" => options.modes = vec!(Mode::from_str(value()?)?),
+				"--engine" => options.engine = Engine::from_str(value()?)?,
+				"--width" => options.width = parse_size("--width", value()?)?,
+				"--height" => options.height = parse_size("--height", value()?)?,
+				"--image" => {
+					let path = value()?;
+					image::Format::from_path(path)?;
+					options.image = Some(path.clone());
+				},
+				"--animate" => options.animation = Some(value()?.clone()),
+				"--every" => options.every = parse_count("--every", value()?)?,
+				"--downscale" => options.downscale = parse_count("--downscale", value()?)?,
+				"--threads" => options.threads = parse_count("--threads", value()?)?,
+				"--explore" => options.explore = true,
+				"--contributions" => options.contributions = true,
+				_ => return Err(format!("unknown option {:?}", arg)),
+			}
+		}
+
+		check_size(options.width, options.height)?;
+		if (options.image.is_some() || options.animation.is_some()) && options.engine != Engine::Grid {
+			return Err("--image and --animate need the grid engine".to_string());
+		}
+		if options.explore && (options.modes.len() > 1 || options.engine != Engine::Grid) {
+			return Err("--explore needs --mode and the grid engine".to_string());
+		}
+		if options.threads > 1 && (options.animation.is_some() || options.explore || options.engine != Engine::Grid) {
+			return Err("--threads needs the grid engine and cannot be used with --animate or --explore".to_string());
+		}
+
+		Ok(options)
+	}
+
+	/// Returns the file an image of `mode` should be saved to, given the `path` from the command
+	/// line.
+	fn image_path(&self, path: &Option<String>, mode: Mode) -> Option<String> {
+		use std::path::Path;
+
+		match *path {
+			Some(ref path) if self.modes.len() > 1 => {
+				let path = Path::new(path);
+				let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+				let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+				let file_name = format!("{}-{}.{}", stem, mode, extension);
+				Some(path.with_file_name(file_name).display().to_string())
+			},
+			ref path => path.clone(),
+		}
+	}
+}
+
+/// Parses the value of a grid size option, which must be at least one light.
+fn parse_size(option: &str, value: &str) -> Result<usize, String> {
+	use std::str::FromStr;
+
+	match usize::from_str(value) {
+		Ok(size) if size > 0 => Ok(size),
+		_ => Err(format!("{} must be a whole number of lights greater than zero, not {:?}", option, value)),
+	}
+}
+
+/// Checks that a `width` by `height` grid holds no more than `grid::MAX_LIGHTS`.
+fn check_size(width: usize, height: usize) -> Result<(), String> {
+	match grid::lights(width, height) {
+		Some(_) => Ok(()),
+		None => Err(format!("a grid of {} by {} lights is too large", width, height)),
+	}
+}
+
+/// Parses the value of an option that counts something, which must be at least one.
+fn parse_count(option: &str, value: &str) -> Result<usize, String> {
+	use std::str::FromStr;
+
+	match usize::from_str(value) {
+		Ok(count) if count > 0 => Ok(count),
+		_ => Err(format!("{} must be a whole number greater than zero, not {:?}", option, value)),
+	}
+}
+
+/// Runs the program on the grid engine, saving any pictures asked for in `options`.
+fn run_grid(instructions: &[Instructions], mode: Mode, options: &Options) -> Result<u64, String> {
+	let grid = match options.image_path(&options.animation, mode) {
+		Some(path) => {
+			let mut recorder = animation::Recorder::create(&path, options.every, options.downscale)?;
+			let grid = light_grid_with(instructions, mode, options.width, options.height,
+				|step, grid| recorder.record(step, grid))?;
+
+			let frames = recorder.finish(instructions.len(), &grid)?;
+			println!("Saved {} frames of the light show to {}", frames, path);
+			grid
+		},
+		None if options.threads > 1 => {
+			parallel::parallel_grid(instructions, mode, options.width, options.height, options.threads)?
+		},
+		None => light_grid(instructions, mode, options.width, options.height)?,
+	};
+
+	if let Some(path) = options.image_path(&options.image, mode) {
+		image::Image::render(&grid).save(&path)?;
+		println!("Saved a picture of the lights to {}", path);
+	}
+
+	Ok(grid.total())
+}
+
+/// Prints the contribution of each instruction in `mode`, see `contribution::contributions`.
+fn print_contributions(instructions: &[Instructions], mode: Mode, options: &Options) -> Result<(), String> {
+	let contributions = contribution::contributions(instructions, mode, options.width, options.height)?;
+
+	println!("What each instruction adds to the answer in {} mode:", mode);
+	for (i, (instruction, contribution)) in instructions.iter().zip(&contributions).enumerate() {
+		println!("{:5} {:+10}  {}", i + 1, contribution.difference, instruction);
+	}
+
+	let unused: Vec<String> = contributions.iter()
+		.enumerate()
+		.filter(|&(_, contribution)| !contribution.changes_grid)
+		.map(|(i, _)| (i + 1).to_string())
+		.collect();
+	if unused.is_empty() {
+		println!("Every instruction changes the final lights");
+	} else {
+		println!("Instructions with no effect on the final lights: {}", unused.join(", "));
+	}
+
+	Ok(())
+}
 
 fn main() {
 	use std::fs::File;
 	use std::io::prelude::*;
 
+	let args: Vec<String> = std::env::args().skip(1).collect();
+	if args.first().map(|arg| arg.as_str()) == Some("life") {
+		if let Err(e) = life::main(&args[1..]) {
+			println!("{}", e);
+			println!("{}", life::USAGE);
+		}
+		return
+	}
+
+	let options = match Options::parse(&args) {
+		Ok(options) => options,
+		Err(e) => {
+			println!("{}", e);
+			println!("{}", USAGE);
+			return
+		}
+	};
+
 	let mut f = File::open("input.txt").unwrap();
 	let mut buffer = String::new();
 
 	f.read_to_string(&mut buffer).unwrap();
 
 	let instructions = parse_program(&buffer);
-	let lights_on = run_program(&instructions);
-	println!("There should be {:?} lights on after running the program", lights_on);
+	if options.explore {
+		let result = history::History::new(&instructions, options.modes[0], options.width, options.height)
+			.and_then(|mut history| {
+				let stdin = std::io::stdin();
+				history::explore(&mut history, stdin.lock(), &mut std::io::stdout()).map_err(|e| e.to_string())
+			});
+		if let Err(e) = result {
+			println!("Failed to explore the program: {}", e);
+		}
+		return
+	}
+
+	for &mode in &options.modes {
+		let total = match options.engine {
+			Engine::Grid => run_grid(&instructions, mode, &options),
+			Engine::Compressed => compressed::run_compressed(&instructions, mode, options.width, options.height),
+			Engine::Plane => plane::run_plane(&instructions, mode),
+		};
+		match (mode, total) {
+			(Mode::OnOff, Ok(total)) => println!("There should be {:?} lights on after running the program", total),
+			(Mode::Brightness, Ok(total)) => println!("The total brightness after running the program is {:?}", total),
+			(_, Err(e)) => {
+				println!("Failed to run the program: {}", e);
+				return
+			},
+		}
+
+		if options.contributions {
+			if let Err(e) = print_contributions(&instructions, mode, &options) {
+				println!("Failed to work out the contributions: {}", e);
+				return
+			}
+		}
+	}
 }
 
-type Coordinate = (u32,u32);
+type Coordinate = (i64,i64);
 
+/// An instruction is a command applied to a region of lights.
+///
+/// `turn on`, `turn off` and `toggle` are the commands from Santa's instructions. `set <n>`,
+/// `dim <n>` and `brighten <n>` give finer control over the brightness of the lights; in on/off
+/// mode `set` turns lights on for any brightness above zero and off otherwise, `brighten` turns
+/// lights on and `dim` turns them off (both doing nothing when `n` is zero).
 #[derive(Copy, Clone, PartialEq, Debug)]
 enum Instructions {
-	TurnOn(Coordinate, Coordinate),
-	Toggle(Coordinate, Coordinate),
-	TurnOff(Coordinate, Coordinate),
+	TurnOn(Region),
+	Toggle(Region),
+	TurnOff(Region),
+	Set(u32, Region),
+	Dim(u32, Region),
+	Brighten(u32, Region),
+}
+
+impl Instructions {
+	/// Returns the region the instruction applies to.
+	fn region(&self) -> Region {
+		match *self {
+			Instructions::TurnOn(region) => region,
+			Instructions::Toggle(region) => region,
+			Instructions::TurnOff(region) => region,
+			Instructions::Set(_, region) => region,
+			Instructions::Dim(_, region) => region,
+			Instructions::Brighten(_, region) => region,
+		}
+	}
+
+	/// Returns the same instruction applied to a different region.
+	fn with_region(&self, region: Region) -> Instructions {
+		match *self {
+			Instructions::TurnOn(..) => Instructions::TurnOn(region),
+			Instructions::Toggle(..) => Instructions::Toggle(region),
+			Instructions::TurnOff(..) => Instructions::TurnOff(region),
+			Instructions::Set(n, _) => Instructions::Set(n, region),
+			Instructions::Dim(n, _) => Instructions::Dim(n, region),
+			Instructions::Brighten(n, _) => Instructions::Brighten(n, region),
+		}
+	}
+
+	/// Checks that the instruction lies inside a `width` by `height` grid.
+	///
+	/// The corners of a rectangle may be given in any order, so the instruction is returned with
+	/// its region normalized, see `Region::normalize`.
+	fn validate(&self, width: usize, height: usize) -> Result<Instructions, String> {
+		let ((x1, y1), (x2, y2)) = self.region().bounds();
+
+		if x1 < 0 {
+			Err(format!("`{}` reaches x = {} but the grid starts at 0", self, x1))
+		} else if y1 < 0 {
+			Err(format!("`{}` reaches y = {} but the grid starts at 0", self, y1))
+		} else if x2 >= width as i64 {
+			Err(format!("`{}` reaches x = {} but the grid is only {} lights wide", self, x2, width))
+		} else if y2 >= height as i64 {
+			Err(format!("`{}` reaches y = {} but the grid is only {} lights high", self, y2, height))
+		} else {
+			Ok(self.with_region(self.region().normalize()))
+		}
+	}
+
+	/// Describes what the instruction does, for reporting progress.
+	fn describe(&self) -> String {
+		let region = self.region().describe();
+		match *self {
+			Instructions::TurnOn(..) => format!("Turning on {}", region),
+			Instructions::Toggle(..) => format!("Toggling {}", region),
+			Instructions::TurnOff(..) => format!("Turning off {}", region),
+			Instructions::Set(n, _) => format!("Setting {} to {}", region, n),
+			Instructions::Dim(n, _) => format!("Dimming {} by {}", region, n),
+			Instructions::Brighten(n, _) => format!("Brightening {} by {}", region, n),
+		}
+	}
+}
+
+impl std::fmt::Display for Instructions {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match *self {
+			Instructions::TurnOn(region) => write!(f, "turn on {}", region),
+			Instructions::Toggle(region) => write!(f, "toggle {}", region),
+			Instructions::TurnOff(region) => write!(f, "turn off {}", region),
+			Instructions::Set(n, region) => write!(f, "set {} {}", n, region),
+			Instructions::Dim(n, region) => write!(f, "dim {} {}", n, region),
+			Instructions::Brighten(n, region) => write!(f, "brighten {} {}", n, region),
+		}
+	}
+}
+
+#[test]
+fn options_test() {
+	let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<String>>();
+
+	let options = Options::parse(&args(&["--width", "20", "--height", "30"])).unwrap();
+	assert_eq!((20, 30), (options.width, options.height));
+	let huge = format!("{}", usize::MAX / 2);
+	assert_eq!(Some(format!("a grid of {} by 1000 lights is too large", huge)),
+		Options::parse(&args(&["--width", &huge])).err());
+	assert_eq!(Some(format!("a grid of {} by {} lights is too large", huge, huge)),
+		Options::parse(&args(&["--width", &huge, "--height", &huge])).err());
+}
+
+#[test]
+fn validate_test() {
+	let instruction = Instructions::Toggle(Region::Rectangle((10, 2), (3, 7)));
+	assert_eq!(Ok(Instructions::Toggle(Region::Rectangle((3, 2), (10, 7)))), instruction.validate(11, 8));
+
+	assert_eq!(Err("`toggle 10,2 through 3,7` reaches x = 10 but the grid is only 10 lights wide".to_string()),
+		instruction.validate(10, 8));
+	assert_eq!(Err("`toggle 10,2 through 3,7` reaches y = 7 but the grid is only 7 lights high".to_string()),
+		instruction.validate(11, 7));
+
+	let instruction = Instructions::Set(4, Region::Circle((3, 3), 3));
+	assert_eq!(Ok(instruction), instruction.validate(7, 7));
+	assert!(instruction.validate(6, 7).is_err());
+	assert_eq!(Err("`set 4 center 2,3 radius 3` reaches x = -1 but the grid starts at 0".to_string()),
+		Instructions::Set(4, Region::Circle((2, 3), 3)).validate(10, 10));
 }
 
 fn parse_program(input: &str) -> Vec<Instructions> {
 	use regex::Regex;
 
 	let re = Regex::new(r"(?x)
-		(?P<instruction>turn\son|toggle|turn\soff)
-		\s*
-		(?P<start_begin_range>\d*)
-		,
-		(?P<end_begin_range>\d*)
-		\s*
-		through
+		^\s*
+		(?P<instruction>turn\son|toggle|turn\soff|set|dim|brighten)
 		\s*
-		(?P<start_end_range>\d*)
-		,
-		(?P<end_end_range>\d*)
-		.*").unwrap();
+		(?P<amount>\d*)
+		\s+
+		(?:
+			(?P<shape>line|outline)?
+			\s*
+			(?P<start_begin_range>-?\d+)
+			,
+			(?P<end_begin_range>-?\d+)
+			\s*
+			through
+			\s*
+			(?P<start_end_range>-?\d+)
+			,
+			(?P<end_end_range>-?\d+)
+		|
+			center
+			\s*
+			(?P<center_x>-?\d+)
+			,
+			(?P<center_y>-?\d+)
+			\s*
+			radius
+			\s*
+			(?P<radius>\d+)
+		|
+			(?P<point_x>-?\d+)
+			,
+			(?P<point_y>-?\d+)
+		)
+		\s*$").unwrap();
 
 	let mut instructions: Vec<Instructions> = Vec::new();
 	for line in input.lines() {
@@ -82,7 +503,22 @@ fn parse_program(input: &str) -> Vec<Instructions> {
 									captures.name("start_end_range"),
 									captures.name("end_end_range"));
 
-				match parse_instruction(captures.name("instruction"), start_coord, end_coord) {
+				let region = match (start_coord, end_coord, captures.name("shape")) {
+					(Some(start), Some(end), None) => Some(Region::Rectangle(start, end)),
+					(Some(start), Some(end), Some("line")) => Some(Region::Line(start, end)),
+					(Some(start), Some(end), Some("outline")) => Some(Region::Outline(start, end)),
+					_ => {
+						let center = parse_coordinate(captures.name("center_x"), captures.name("center_y"));
+						let point = parse_coordinate(captures.name("point_x"), captures.name("point_y"));
+						match (center, parse_number(captures.name("radius")), point) {
+							(Some(center), Some(radius), _) => Some(Region::Circle(center, radius)),
+							(_, _, Some(point)) => Some(Region::Point(point)),
+							_ => None,
+						}
+					},
+				};
+
+				match parse_instruction(captures.name("instruction"), captures.name("amount"), region) {
 					Some(i) => {
 						instructions.push(i);
 					},
@@ -97,20 +533,35 @@ fn parse_program(input: &str) -> Vec<Instructions> {
 
 fn parse_instruction(
 	instruction: Option<&str>, 
-	start: Option<Coordinate>, 
-	end: Option<Coordinate>) -> Option<Instructions> {
+	amount: Option<&str>,
+	region: Option<Region>) -> Option<Instructions> {
 
-	match (instruction, start, end) {
-		(Some(instruction_raw), Some(start_coord), Some(end_coord)) => {
+	match (instruction, amount.unwrap_or(""), region) {
+		(Some(instruction_raw), "", Some(region)) => {
 			match instruction_raw {
 				"turn on" => {
-					Some(Instructions::TurnOn(start_coord, end_coord))
+					Some(Instructions::TurnOn(region))
 				},
 				"turn off" => {
-					Some(Instructions::TurnOff(start_coord, end_coord))
+					Some(Instructions::TurnOff(region))
 				},
 				"toggle" => {
-					Some(Instructions::Toggle(start_coord, end_coord))
+					Some(Instructions::Toggle(region))
+				},
+				_ => None
+			}
+		},
+		(Some(instruction_raw), amount, Some(region)) => {
+			let amount = parse_number(Some(amount))?;
+			match instruction_raw {
+				"set" => {
+					Some(Instructions::Set(amount, region))
+				},
+				"dim" => {
+					Some(Instructions::Dim(amount, region))
+				},
+				"brighten" => {
+					Some(Instructions::Brighten(amount, region))
 				},
 				_ => None
 			}
@@ -119,16 +570,19 @@ fn parse_instruction(
 	}
 }
 
+fn parse_number(number: Option<&str>) -> Option<u32> {
+	use std::str::FromStr;
+
+	number.and_then(|n| u32::from_str(n).ok())
+}
+
+/// Parses a pair of coordinates, which may be negative but must fit in 32 bits.
 fn parse_coordinate(start: Option<&str>, end: Option<&str>) -> Option<Coordinate> {
 	use std::str::FromStr;
 
-	match (start, end) {
-		(Some(s), Some(e)) => {
-			match (u32::from_str(s), u32::from_str(e)) {
-				(Ok(x), Ok(y)) => Some((x, y)),
-				_ => None
-			}
-		},
+	let parse = |n: Option<&str>| n.and_then(|n| i32::from_str(n).ok());
+	match (parse(start), parse(end)) {
+		(Some(x), Some(y)) => Some((x as i64, y as i64)),
 		_ => None
 	}
 }
@@ -137,72 +591,108 @@ fn parse_coordinate(start: Option<&str>, end: Option<&str>) -> Option<Coordinate
 fn parse_program_test() {
 	let test_case = "turn on 0,0 through 999,999\ntoggle 0,0 through 999,0\nturn off 499,499 through 500,500";
 	let expect = vec!(
-		Instructions::TurnOn((0,0), (999,999)),
-		Instructions::Toggle((0,0), (999,0)),
-		Instructions::TurnOff((499,499),(500,500))
+		Instructions::TurnOn(Region::Rectangle((0,0), (999,999))),
+		Instructions::Toggle(Region::Rectangle((0,0), (999,0))),
+		Instructions::TurnOff(Region::Rectangle((499,499),(500,500)))
 	);
 
 	assert_eq!(expect, parse_program(test_case));
+
+	let test_case = "set 5 0,0 through 9,9\ndim 2 3,4\nbrighten 10 line 0,0 through 9,9\n\
+		turn on outline 1,1 through 8,8\ntoggle center 5,5 radius 3\nset 0 center 1,2 radius 0";
+	let expect = vec!(
+		Instructions::Set(5, Region::Rectangle((0,0), (9,9))),
+		Instructions::Dim(2, Region::Point((3,4))),
+		Instructions::Brighten(10, Region::Line((0,0), (9,9))),
+		Instructions::TurnOn(Region::Outline((1,1), (8,8))),
+		Instructions::Toggle(Region::Circle((5,5), 3)),
+		Instructions::Set(0, Region::Circle((1,2), 0)),
+	);
+
+	assert_eq!(expect, parse_program(test_case));
+
+	let test_case = "turn on -5,3 through 2,-7\ntoggle center -1,-1 radius 2\nset 3 -2147483648,0";
+	let expect = vec!(
+		Instructions::TurnOn(Region::Rectangle((-5,3), (2,-7))),
+		Instructions::Toggle(Region::Circle((-1,-1), 2)),
+		Instructions::Set(3, Region::Point((-2147483648,0))),
+	);
+
+	assert_eq!(expect, parse_program(test_case));
+
+	let test_case = "set 0,0 through 9,9\nturn on 5 0,0 through 9,9\nturn on 2147483648,0\ntoggle line 3,4\ndim 2 center 1,1\nturn on 1,2 through";
+	assert_eq!(Vec::<Instructions>::new(), parse_program(test_case));
+
+	for instruction in parse_program(include_str!("../input.txt")) {
+		assert_eq!(vec!(instruction), parse_program(&instruction.to_string()));
+	}
 }
 
-fn run_program(instructions: &[Instructions]) -> u32 {
-	const WIDTH: usize = 1000;
-	const HEIGHT: usize = 1000;
+/// Runs the instructions against a `width` by `height` grid of lights, interpreting them according
+/// to `mode`, and returns the final grid of lights.
+///
+/// Fails without running anything if an instruction lies outside of the grid.
+fn light_grid(instructions: &[Instructions], mode: Mode, width: usize, height: usize) -> Result<Grid, String> {
+	light_grid_with(instructions, mode, width, height, |_, _| Ok(()))
+}
 
-	let mut grid: Vec<u32> = vec!(0; WIDTH*HEIGHT);
+/// Runs the instructions as `light_grid` does, calling `after` with the number of instructions run
+/// so far and the grid after each one.
+fn light_grid_with<F>(instructions: &[Instructions], mode: Mode, width: usize, height: usize, mut after: F)
+	-> Result<Grid, String> where F: FnMut(usize, &Grid) -> Result<(), String> {
 
-	for instruction in instructions {
-		match *instruction {
-			Instructions::TurnOn((start_x, start_y), (end_x, end_y)) => {
-				println!("Turning on lights from ({:?},{:?}) to ({:?},{:?})", 
-					start_x, start_y, end_x, end_y);
+	let instructions = validate_program(instructions, width, height)?;
+	let mut grid = Grid::new(mode, width, height);
 
-				for y in start_y..end_y + 1 {
-					for x in start_x..end_x + 1 {
-						let i = (x + (y * WIDTH as u32)) as usize;
-						grid[i] = grid[i] + 1;
-					}
-				}
-			},
-			Instructions::TurnOff((start_x, start_y), (end_x, end_y)) => {
-				println!("Turning off lights from ({:?},{:?}) to ({:?},{:?})", 
-					start_x, start_y, end_x, end_y);
-
-				for y in start_y..end_y + 1 {
-					for x in start_x..end_x + 1 {
-						let i = (x + (y * WIDTH as u32)) as usize;
-						if grid[i] > 0 {
-							grid[i] = grid[i] - 1;
-						}
-					}
-				}
-			},
-			Instructions::Toggle((start_x, start_y), (end_x, end_y)) => {
-				println!("Toggling lights from ({:?},{:?}) to ({:?},{:?})", 
-					start_x, start_y, end_x, end_y);
-
-				for y in start_y..end_y + 1 {
-					for x in start_x..end_x + 1{
-						let i = (x + (y * WIDTH as u32)) as usize;
-						grid[i] = grid[i] + 2;
-					}
-				}
-			},
-		}
+	for (i, instruction) in instructions.iter().enumerate() {
+		println!("{}", instruction.describe());
+
+		grid.apply(instruction);
+		after(i + 1, &grid)?;
 	}
 
-	grid.iter().sum::<u32>() as u32
+	Ok(grid)
+}
+
+/// Validates every instruction against a `width` by `height` grid, see `Instructions::validate`.
+fn validate_program(instructions: &[Instructions], width: usize, height: usize) -> Result<Vec<Instructions>, String> {
+	instructions.iter()
+		.enumerate()
+		.map(|(i, instruction)| {
+			instruction.validate(width, height).map_err(|e| format!("instruction {}: {}", i + 1, e))
+		})
+		.collect()
 }
 
 #[test]
-fn run_program_test() {
+fn light_grid_test() {
 	let test_case = vec!(
-		Instructions::TurnOn((0,0), (999,999)),
-		Instructions::Toggle((0,0), (999,0)),
-		Instructions::TurnOff((499,499),(500,500))
+		Instructions::TurnOn(Region::Rectangle((0,0), (999,999))),
+		Instructions::Toggle(Region::Rectangle((0,0), (999,0))),
+		Instructions::TurnOff(Region::Rectangle((499,499),(500,500)))
 	);
 
-	let expect = 998000;
+	assert_eq!(Ok(998996), light_grid(&test_case, Mode::OnOff, 1000, 1000).map(|grid| grid.total()));
+	assert_eq!(Ok(1001996), light_grid(&test_case, Mode::Brightness, 1000, 1000).map(|grid| grid.total()));
+
+	let test_case = vec!(
+		Instructions::TurnOn(Region::Rectangle((0,0), (2,2))),
+		Instructions::TurnOff(Region::Rectangle((2,0), (0,2))),
+		Instructions::Toggle(Region::Rectangle((3,0), (0,2))),
+	);
+
+	assert_eq!(Ok(12), light_grid(&test_case, Mode::OnOff, 4, 3).map(|grid| grid.total()));
+	assert_eq!(Err("instruction 3: `toggle 3,0 through 0,2` reaches x = 3 but the grid is only 3 lights wide".to_string()),
+		light_grid(&test_case, Mode::OnOff, 3, 3).map(|grid| grid.total()));
+
+	let test_case = vec!(
+		Instructions::Set(7, Region::Rectangle((0,0), (9,9))),
+		Instructions::Dim(3, Region::Outline((0,0), (9,9))),
+		Instructions::Brighten(2, Region::Line((0,0), (9,9))),
+		Instructions::Toggle(Region::Circle((5,5), 1)),
+		Instructions::Set(0, Region::Point((5,5))),
+	);
 
-	assert_eq!(expect, run_program(&test_case));
+	assert_eq!(Ok(100 - 36 + 2 - 5), light_grid(&test_case, Mode::OnOff, 10, 10).map(|grid| grid.total()));
+	assert_eq!(Ok(700 - 36 * 3 + 20 + 5 * 2 - 11), light_grid(&test_case, Mode::Brightness, 10, 10).map(|grid| grid.total()));
 }
@@ -0,0 +1,101 @@
+//! Provides an evaluator that shares the grid between several threads.
+//!
+//! The final state of a row only depends on the instructions that cover it, so the grid is cut into
+//! bands of whole rows and each band is run on its own thread. Every thread reads the whole program
+//! but only applies the part of each rectangle that falls inside its band. The bands are stacked
+//! back together once every thread is done, giving exactly the grid the sequential engine would.
+
+use std::thread;
+
+use grid::{Effect, Grid, Mode};
+use {Coordinate, Instructions};
+
+/// Runs the instructions against a `width` by `height` grid split between `threads` threads, and
+/// returns the final grid.
+///
+/// Fails without running anything if an instruction lies outside of the grid.
+pub fn parallel_grid(instructions: &[Instructions], mode: Mode, width: usize, height: usize, threads: usize)
+	-> Result<Grid, String> {
+
+	let instructions = ::validate_program(instructions, width, height)?;
+	let rectangles: Vec<(Effect, Coordinate, Coordinate)> = instructions.iter()
+		.flat_map(|instruction| {
+			let effect = Effect::of(instruction, mode);
+			instruction.region().rectangles().into_iter().map(move |(start, end)| (effect, start, end))
+		})
+		.collect();
+
+	if height == 0 {
+		return Ok(Grid::new(mode, width, height));
+	}
+
+	let band_height = height.div_ceil(threads.clamp(1, height));
+	let bands = thread::scope(|scope| {
+		let handles: Vec<_> = (0..height).step_by(band_height)
+			.map(|top| {
+				let rectangles = &rectangles;
+				let bottom = (top + band_height).min(height);
+				scope.spawn(move || run_band(rectangles, mode, width, top, bottom))
+			})
+			.collect();
+
+		handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+	});
+
+	Ok(Grid::stack(bands))
+}
+
+/// Runs the rectangles over the rows from `top` up to but not including `bottom`, returning a grid
+/// holding just those rows.
+fn run_band(rectangles: &[(Effect, Coordinate, Coordinate)], mode: Mode, width: usize, top: usize, bottom: usize)
+	-> Grid {
+
+	let mut band = Grid::new(mode, width, bottom - top);
+	let (top, bottom) = (top as i64, bottom as i64);
+
+	for &(effect, start, end) in rectangles {
+		if end.1 < top || start.1 >= bottom {
+			continue;
+		}
+
+		let start = (start.0, start.1.max(top) - top);
+		let end = (end.0, end.1.min(bottom - 1) - top);
+		band.apply_effect(effect, start, end);
+	}
+
+	band
+}
+
+#[test]
+fn parallel_grid_test() {
+	use compressed::random_instructions;
+	use region::Region;
+
+	for seed in 0..5 {
+		let instructions = random_instructions(50, 300, seed);
+		for &mode in &[Mode::OnOff, Mode::Brightness] {
+			let expected = ::light_grid(&instructions, mode, 300, 300).unwrap();
+			for &threads in &[1, 2, 3, 7, 300, 1000] {
+				assert_eq!(Ok(expected.clone()), parallel_grid(&instructions, mode, 300, 300, threads));
+			}
+		}
+	}
+
+	let instructions = vec!(
+		Instructions::Set(3, Region::Circle((5, 5), 5)),
+		Instructions::Toggle(Region::Line((0, 10), (10, 0))),
+		Instructions::Dim(1, Region::Outline((1, 1), (9, 9))),
+	);
+	for &mode in &[Mode::OnOff, Mode::Brightness] {
+		assert_eq!(::light_grid(&instructions, mode, 11, 11), parallel_grid(&instructions, mode, 11, 11, 4));
+	}
+
+	assert!(parallel_grid(&instructions, Mode::OnOff, 11, 10, 4).is_err());
+	assert_eq!(Ok(Grid::new(Mode::OnOff, 5, 0)), parallel_grid(&[], Mode::OnOff, 5, 0, 4));
+}
+
+#[bench]
+fn bench_parallel_large_rectangles(b: &mut ::test::Bencher) {
+	let instructions = ::compressed::random_instructions(100, 4000, 7);
+	b.iter(|| parallel_grid(&instructions, Mode::Brightness, 4000, 4000, 4));
+}
@@ -0,0 +1,262 @@
+//! Provides a sparse, unbounded plane of lights addressed by signed coordinates.
+//!
+//! Only the places where the lights change are stored. A row is a list of runs, each light taking
+//! the value of the nearest run starting at or to the left of it, and neighbouring rows that are
+//! identical share a single band in the same way. Applying a rectangle only updates the bands and
+//! runs it crosses, however large it is, so the plane never allocates the bounding box of the
+//! program. Splitting a band at the edge of a rectangle copies its row, though, and inserting or
+//! removing a band or run shifts the rest of its list.
+//!
+//! Lines and circles are applied a row at a time, so a region may cover at most `MAX_ROWS` rows.
+
+use grid::{Effect, Mode};
+use {Coordinate, Instructions};
+
+/// The most rectangles a single instruction may be broken into, see `Region::count_rectangles`.
+pub const MAX_ROWS: u64 = 1 << 20;
+
+/// A Row holds the lights of a row as runs of equal value.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Row {
+	/// `(x, value)` pairs sorted by `x`: each light from `x` up to the next pair has `value`.
+	/// Lights before the first pair are 0, and the last pair always has the value 0.
+	runs: Vec<(i64, u32)>,
+}
+
+impl Row {
+	#[cfg(test)]
+	fn get(&self, x: i64) -> u32 {
+		match self.runs.binary_search_by_key(&x, |&(start, _)| start) {
+			Ok(i) => self.runs[i].1,
+			Err(0) => 0,
+			Err(i) => self.runs[i - 1].1,
+		}
+	}
+
+	/// Makes sure that a run starts at `x`, returning its index.
+	fn split(&mut self, x: i64) -> usize {
+		match self.runs.binary_search_by_key(&x, |&(start, _)| start) {
+			Ok(i) => i,
+			Err(i) => {
+				let value = if i == 0 { 0 } else { self.runs[i - 1].1 };
+				self.runs.insert(i, (x, value));
+				i
+			},
+		}
+	}
+
+	/// Applies an effect to the lights from `x1` to `x2` inclusive.
+	fn apply(&mut self, effect: Effect, x1: i64, x2: i64) {
+		let first = self.split(x1);
+		let last = self.split(x2 + 1);
+		for run in &mut self.runs[first..last] {
+			run.1 = effect.apply(run.1);
+		}
+
+		// Runs that carry on with the value before them are no longer needed. Only the runs that
+		// changed, and the one after them, can do so.
+		for i in (first..last + 1).rev() {
+			let previous = if i == 0 { 0 } else { self.runs[i - 1].1 };
+			if self.runs[i].1 == previous {
+				self.runs.remove(i);
+			}
+		}
+	}
+
+	fn total(&self) -> u128 {
+		self.runs.windows(2)
+			.map(|pair| (pair[1].0 - pair[0].0) as u128 * pair[0].1 as u128)
+			.sum()
+	}
+}
+
+/// A Plane is an unbounded grid of lights in either mode, all of them off to begin with.
+///
+/// Coordinates may be anything up to, but not including, `i64::MAX`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Plane {
+	mode: Mode,
+	/// `(y, row)` pairs sorted by `y`: each row from `y` up to the next pair is `row`. Rows above
+	/// the first pair are dark, and the last pair always holds a dark row.
+	bands: Vec<(i64, Row)>,
+}
+
+impl Plane {
+	pub fn new(mode: Mode) -> Plane {
+		Plane { mode, bands: Vec::new() }
+	}
+
+	/// Returns the value of the light at `(x, y)`, see `Grid::get`.
+	#[cfg(test)]
+	pub fn get(&self, x: i64, y: i64) -> u32 {
+		match self.bands.binary_search_by_key(&y, |&(start, _)| start) {
+			Ok(i) => self.bands[i].1.get(x),
+			Err(0) => 0,
+			Err(i) => self.bands[i - 1].1.get(x),
+		}
+	}
+
+	/// Applies a single instruction to the plane.
+	///
+	/// Fails without changing any light if the region of the instruction covers more than
+	/// `MAX_ROWS` rows.
+	pub fn apply(&mut self, instruction: &Instructions) -> Result<(), String> {
+		let region = instruction.region();
+		let rows = region.count_rectangles();
+		if rows > MAX_ROWS {
+			return Err(format!("`{}` covers {} rows, but the plane takes at most {} at once", instruction, rows, MAX_ROWS));
+		}
+
+		let effect = Effect::of(instruction, self.mode);
+		for (start, end) in region.rectangles() {
+			self.apply_effect(effect, start, end);
+		}
+		Ok(())
+	}
+
+	/// Applies an effect to every light in the inclusive rectangle between the corners `start` and
+	/// `end`.
+	pub fn apply_effect(&mut self, effect: Effect, start: Coordinate, end: Coordinate) {
+		let (x1, x2) = (start.0.min(end.0), start.0.max(end.0));
+		let (y1, y2) = (start.1.min(end.1), start.1.max(end.1));
+
+		let first = self.split(y1);
+		let last = self.split(y2 + 1);
+		for band in &mut self.bands[first..last] {
+			band.1.apply(effect, x1, x2);
+		}
+
+		// Bands that repeat the rows before them are no longer needed. Only the bands that
+		// changed, and the one after them, can do so.
+		for i in (first..last + 1).rev() {
+			let repeated = if i == 0 { self.bands[i].1.runs.is_empty() } else { self.bands[i].1 == self.bands[i - 1].1 };
+			if repeated {
+				self.bands.remove(i);
+			}
+		}
+	}
+
+	/// Makes sure that a band starts at row `y`, returning its index.
+	fn split(&mut self, y: i64) -> usize {
+		match self.bands.binary_search_by_key(&y, |&(start, _)| start) {
+			Ok(i) => i,
+			Err(i) => {
+				let row = if i == 0 { Row::default() } else { self.bands[i - 1].1.clone() };
+				self.bands.insert(i, (y, row));
+				i
+			},
+		}
+	}
+
+	/// Returns the number of lights lit in on/off mode, or the total brightness in brightness mode.
+	pub fn total(&self) -> u128 {
+		self.bands.windows(2)
+			.map(|pair| (pair[1].0 - pair[0].0) as u128 * pair[0].1.total())
+			.sum()
+	}
+
+	/// Returns the corners of the smallest rectangle holding every light that is on, if any are.
+	pub fn bounds(&self) -> Option<(Coordinate, Coordinate)> {
+		let rows = self.bands.iter().map(|(_, row)| &row.runs).filter(|runs| !runs.is_empty());
+		let left = rows.clone().map(|runs| runs[0].0).min()?;
+		let right = rows.map(|runs| runs[runs.len() - 1].0 - 1).max()?;
+
+		let top = self.bands[0].0;
+		let bottom = self.bands[self.bands.len() - 1].0 - 1;
+		Some(((left, top), (right, bottom)))
+	}
+}
+
+/// Runs the instructions on an unbounded plane and returns the number of lights lit in on/off mode,
+/// or the total brightness in brightness mode.
+///
+/// Fails if the answer is too large to fit in 64 bits.
+pub fn run_plane(instructions: &[Instructions], mode: Mode) -> Result<u64, String> {
+	let mut plane = Plane::new(mode);
+	for (i, instruction) in instructions.iter().enumerate() {
+		plane.apply(instruction).map_err(|e| format!("instruction {}: {}", i + 1, e))?;
+	}
+
+	if let Some(((x1, y1), (x2, y2))) = plane.bounds() {
+		println!("The lights that are on lie within {},{} through {},{}", x1, y1, x2, y2);
+	}
+
+	let total = plane.total();
+	if total > u64::MAX as u128 {
+		return Err(format!("the answer {} does not fit in 64 bits", total));
+	}
+	Ok(total as u64)
+}
+
+#[test]
+fn plane_test() {
+	use region::Region;
+
+	let mut plane = Plane::new(Mode::OnOff);
+	assert_eq!(None, plane.bounds());
+
+	plane.apply(&Instructions::TurnOn(Region::Rectangle((-5, -5), (4, 4)))).unwrap();
+	plane.apply(&Instructions::Toggle(Region::Rectangle((0, 0), (9, 9)))).unwrap();
+	assert_eq!(100 - 25 + 75, plane.total());
+	assert_eq!(1, plane.get(-5, -5));
+	assert_eq!(0, plane.get(-6, -5));
+	assert_eq!(0, plane.get(2, 3));
+	assert_eq!(1, plane.get(9, 9));
+	assert_eq!(0, plane.get(10, 9));
+	assert_eq!(Some(((-5, -5), (9, 9))), plane.bounds());
+
+	plane.apply(&Instructions::TurnOff(Region::Rectangle((-100, -100), (100, 100)))).unwrap();
+	assert_eq!(0, plane.total());
+	assert_eq!(None, plane.bounds());
+	assert!(plane.bands.is_empty());
+
+	let mut plane = Plane::new(Mode::Brightness);
+	let (min, max) = (i32::MIN as i64, i32::MAX as i64);
+	plane.apply(&Instructions::Brighten(3, Region::Rectangle((min, min), (max, max)))).unwrap();
+	plane.apply(&Instructions::Dim(5, Region::Point((0, 0)))).unwrap();
+	assert_eq!(3 * (1u128 << 64) - 3, plane.total());
+	assert_eq!(4, plane.bands.len());
+	assert!(run_plane(&[Instructions::TurnOn(Region::Rectangle((min, min), (max, max)))], Mode::Brightness).is_err());
+
+	// Circles and lines are applied a row at a time, so huge ones are turned down.
+	let mut plane = Plane::new(Mode::OnOff);
+	plane.apply(&Instructions::Toggle(Region::Circle((0, 0), 1000))).unwrap();
+	plane.apply(&Instructions::Toggle(Region::Line((0, 0), (1, MAX_ROWS as i64 - 1)))).unwrap();
+	let error = run_plane(&[Instructions::Toggle(Region::Circle((0, 0), 100000000))], Mode::OnOff);
+	assert_eq!(Err("instruction 1: `toggle center 0,0 radius 100000000` covers 200000001 rows, \
+		but the plane takes at most 1048576 at once".to_string()), error);
+	let line = Instructions::TurnOn(Region::Line((0, min), (0, max)));
+	assert!(plane.apply(&line).is_err());
+	assert!(plane.apply(&Instructions::TurnOn(Region::Circle((0, 0), u32::MAX))).is_err());
+}
+
+#[test]
+fn run_plane_test() {
+	use compressed::random_instructions;
+
+	// Moving a program anywhere on the plane should not change the answer.
+	for seed in 0..10 {
+		let instructions = random_instructions(50, 300, seed);
+		let shifted: Vec<Instructions> = instructions.iter()
+			.map(|instruction| match instruction.region() {
+				::region::Region::Rectangle(start, end) => {
+					let shift = |(x, y): Coordinate| (x - 150, y - 1000000);
+					instruction.with_region(::region::Region::Rectangle(shift(start), shift(end)))
+				},
+				_ => unreachable!(),
+			})
+			.collect();
+
+		for &mode in &[Mode::OnOff, Mode::Brightness] {
+			let expected = ::light_grid(&instructions, mode, 300, 300).unwrap().total();
+			assert_eq!(Ok(expected), run_plane(&instructions, mode));
+			assert_eq!(Ok(expected), run_plane(&shifted, mode));
+		}
+	}
+}
+
+#[bench]
+fn bench_plane_large_rectangles(b: &mut ::test::Bencher) {
+	let instructions = ::compressed::random_instructions(100, 4000, 7);
+	b.iter(|| run_plane(&instructions, Mode::Brightness));
+}
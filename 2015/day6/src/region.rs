@@ -0,0 +1,223 @@
+//! Provides the shapes of light that an instruction can cover.
+//!
+//! Every region is written after the command of an instruction:
+//!
+//! * `x,y through x,y` is a filled rectangle, given by two opposite corners.
+//! * `x,y` is a single light.
+//! * `line x,y through x,y` is a straight line between two lights.
+//! * `outline x,y through x,y` is the one light wide border of a rectangle.
+//! * `center x,y radius r` is a filled circle.
+//!
+//! The engines only deal in rectangles, so each region can be broken down into a list of
+//! rectangles which between them cover each light of the region exactly once. Lines and circles
+//! take a rectangle for each row they cover, see `Region::count_rectangles`.
+
+use std::fmt;
+
+use Coordinate;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Region {
+	Rectangle(Coordinate, Coordinate),
+	Point(Coordinate),
+	Line(Coordinate, Coordinate),
+	Outline(Coordinate, Coordinate),
+	Circle(Coordinate, u32),
+}
+
+impl Region {
+	/// Returns the top left and bottom right corners of the smallest rectangle containing the
+	/// region.
+	pub fn bounds(&self) -> (Coordinate, Coordinate) {
+		match *self {
+			Region::Rectangle((x1, y1), (x2, y2)) |
+			Region::Line((x1, y1), (x2, y2)) |
+			Region::Outline((x1, y1), (x2, y2)) => ((x1.min(x2), y1.min(y2)), (x1.max(x2), y1.max(y2))),
+			Region::Point(point) => (point, point),
+			Region::Circle((x, y), r) => ((x - r as i64, y - r as i64), (x + r as i64, y + r as i64)),
+		}
+	}
+
+	/// Returns the same region with the corners of rectangles and outlines given from top left to
+	/// bottom right.
+	pub fn normalize(&self) -> Region {
+		let corners = |(x1, y1): Coordinate, (x2, y2): Coordinate| {
+			((x1.min(x2), y1.min(y2)), (x1.max(x2), y1.max(y2)))
+		};
+
+		match *self {
+			Region::Rectangle(start, end) => {
+				let (start, end) = corners(start, end);
+				Region::Rectangle(start, end)
+			},
+			Region::Outline(start, end) => {
+				let (start, end) = corners(start, end);
+				Region::Outline(start, end)
+			},
+			region => region,
+		}
+	}
+
+	/// Returns the number of rectangles `rectangles` breaks the region into, without making them.
+	pub fn count_rectangles(&self) -> u64 {
+		match self.normalize() {
+			Region::Rectangle(..) | Region::Point(..) => 1,
+			Region::Line((_, y1), (_, y2)) => (y2 - y1).unsigned_abs() + 1,
+			Region::Outline(..) => self.rectangles().len() as u64,
+			Region::Circle(_, r) => 2 * r as u64 + 1,
+		}
+	}
+
+	/// Breaks the region into rectangles that cover each of its lights exactly once.
+	pub fn rectangles(&self) -> Vec<(Coordinate, Coordinate)> {
+		match self.normalize() {
+			Region::Rectangle(start, end) => vec!((start, end)),
+			Region::Point(point) => vec!((point, point)),
+			Region::Line(start, end) => line_rectangles(start, end),
+			Region::Outline((x1, y1), (x2, y2)) => {
+				let mut rectangles = vec!(((x1, y1), (x2, y1)));
+				if y2 > y1 {
+					rectangles.push(((x1, y2), (x2, y2)));
+				}
+				if y2 > y1 + 1 {
+					rectangles.push(((x1, y1 + 1), (x1, y2 - 1)));
+					if x2 > x1 {
+						rectangles.push(((x2, y1 + 1), (x2, y2 - 1)));
+					}
+				}
+				rectangles
+			},
+			Region::Circle((x, y), r) => {
+				let r = r as i64;
+				(-r..r + 1).map(|dy| {
+					// The square of a radius up to `u32::MAX` does not fit in an i64.
+					let half = isqrt(r as i128 * r as i128 - dy as i128 * dy as i128);
+					((x - half, y + dy), (x + half, y + dy))
+				}).collect()
+			},
+		}
+	}
+
+	/// Describes the lights covered by the region, for reporting progress.
+	pub fn describe(&self) -> String {
+		match *self {
+			Region::Rectangle((x1, y1), (x2, y2)) => format!("lights from ({:?},{:?}) to ({:?},{:?})", x1, y1, x2, y2),
+			Region::Point((x, y)) => format!("the light at ({:?},{:?})", x, y),
+			Region::Line((x1, y1), (x2, y2)) => format!("lights on the line from ({:?},{:?}) to ({:?},{:?})", x1, y1, x2, y2),
+			Region::Outline((x1, y1), (x2, y2)) => format!("lights around the edge from ({:?},{:?}) to ({:?},{:?})", x1, y1, x2, y2),
+			Region::Circle((x, y), r) => format!("lights within {:?} of ({:?},{:?})", r, x, y),
+		}
+	}
+}
+
+impl fmt::Display for Region {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			Region::Rectangle((x1, y1), (x2, y2)) => write!(f, "{},{} through {},{}", x1, y1, x2, y2),
+			Region::Point((x, y)) => write!(f, "{},{}", x, y),
+			Region::Line((x1, y1), (x2, y2)) => write!(f, "line {},{} through {},{}", x1, y1, x2, y2),
+			Region::Outline((x1, y1), (x2, y2)) => write!(f, "outline {},{} through {},{}", x1, y1, x2, y2),
+			Region::Circle((x, y), r) => write!(f, "center {},{} radius {}", x, y, r),
+		}
+	}
+}
+
+/// Returns the largest whole number whose square is at most `n`.
+fn isqrt(n: i128) -> i64 {
+	let mut root = (n as f64).sqrt() as i128;
+	while root * root > n {
+		root -= 1;
+	}
+	while (root + 1) * (root + 1) <= n {
+		root += 1;
+	}
+	root as i64
+}
+
+/// Breaks the line from `start` to `end` into one horizontal run of lights per row, following
+/// Bresenham's algorithm.
+fn line_rectangles(start: Coordinate, end: Coordinate) -> Vec<(Coordinate, Coordinate)> {
+	let (mut x, mut y) = start;
+	let (x2, y2) = end;
+
+	let dx = (x2 - x).abs();
+	let dy = -(y2 - y).abs();
+	let step_x = if x < x2 { 1 } else { -1 };
+	let step_y = if y < y2 { 1 } else { -1 };
+	let mut error = dx + dy;
+
+	let mut rectangles: Vec<(Coordinate, Coordinate)> = Vec::new();
+	let (mut run_start, mut run_end, mut run_y) = (x, x, y);
+
+	loop {
+		if y != run_y {
+			rectangles.push(((run_start.min(run_end), run_y), (run_start.max(run_end), run_y)));
+			run_start = x;
+			run_y = y;
+		}
+		run_end = x;
+
+		if x == x2 && y == y2 {
+			break;
+		}
+
+		let doubled = 2 * error;
+		if doubled >= dy {
+			error += dy;
+			x += step_x;
+		}
+		if doubled <= dx {
+			error += dx;
+			y += step_y;
+		}
+	}
+
+	rectangles.push(((run_start.min(run_end), run_y), (run_start.max(run_end), run_y)));
+	rectangles
+}
+
+/// Counts the lights covered by a list of rectangles.
+#[cfg(test)]
+fn area(rectangles: &[(Coordinate, Coordinate)]) -> u64 {
+	rectangles.iter().map(|&((x1, y1), (x2, y2))| (x2 - x1 + 1) as u64 * (y2 - y1 + 1) as u64).sum()
+}
+
+#[test]
+fn rectangles_test() {
+	assert_eq!(vec!(((1, 2), (5, 6))), Region::Rectangle((5, 2), (1, 6)).rectangles());
+	assert_eq!(vec!(((3, 4), (3, 4))), Region::Point((3, 4)).rectangles());
+
+	assert_eq!(vec!(((0, 0), (1, 0)), ((2, 1), (3, 1))), Region::Line((0, 0), (3, 1)).rectangles());
+	assert_eq!(vec!(((3, 0), (3, 0)), ((2, 1), (2, 1)), ((1, 2), (1, 2))), Region::Line((3, 0), (1, 2)).rectangles());
+	assert_eq!(vec!(((0, 5), (0, 5)), ((0, 4), (0, 4))), Region::Line((0, 5), (0, 4)).rectangles());
+	assert_eq!(8, area(&Region::Line((7, 7), (0, 0)).rectangles()));
+
+	assert_eq!(16 - 4, area(&Region::Outline((0, 0), (3, 3)).rectangles()));
+	assert_eq!(3, area(&Region::Outline((0, 0), (2, 0)).rectangles()));
+	assert_eq!(3, area(&Region::Outline((0, 0), (0, 2)).rectangles()));
+	assert_eq!(4, area(&Region::Outline((0, 0), (1, 1)).rectangles()));
+
+	assert_eq!(vec!(((2, 1), (2, 1)), ((1, 2), (3, 2)), ((2, 3), (2, 3))), Region::Circle((2, 2), 1).rectangles());
+	assert_eq!(1, area(&Region::Circle((2, 2), 0).rectangles()));
+	assert_eq!(((-1, -1), (5, 5)), Region::Circle((2, 2), 3).bounds());
+	assert_eq!(vec!(((-2, -1), (-2, -1)), ((-3, 0), (-1, 0)), ((-2, 1), (-2, 1))), Region::Circle((-2, 0), 1).rectangles());
+	assert_eq!(vec!(((-1, -3), (-1, -3)), ((-2, -2), (-2, -2))), Region::Line((-1, -3), (-2, -2)).rectangles());
+
+	let regions = vec!(Region::Rectangle((5, 2), (1, 6)), Region::Point((3, 4)), Region::Line((0, 5), (7, -4)),
+		Region::Line((0, 0), (9, 0)), Region::Outline((0, 0), (3, 3)), Region::Outline((0, 0), (0, 2)),
+		Region::Outline((0, 0), (1, 0)), Region::Circle((2, 2), 3), Region::Circle((2, 2), 0));
+	for region in regions {
+		assert_eq!(region.rectangles().len() as u64, region.count_rectangles(), "{}", region);
+	}
+	assert_eq!(2 * u32::MAX as u64 + 1, Region::Circle((0, 0), u32::MAX).count_rectangles());
+}
+
+#[test]
+fn isqrt_test() {
+	let roots: Vec<i64> = (0..10).map(isqrt).collect();
+	assert_eq!(vec!(0, 1, 1, 1, 2, 2, 2, 2, 2, 3), roots);
+
+	let r = u32::MAX as i128;
+	assert_eq!(r as i64, isqrt(r * r));
+	assert_eq!(r as i64 - 1, isqrt(r * r - 1));
+}
@@ -57,31 +57,127 @@
 /// ultimately provided to _wire_ `a`?
 
 mod parser;
+mod vectors;
 
 use std::collections::HashMap;
 
+/// Without arguments both puzzle inputs are run. Circuits can also be checked with
+///
+/// * `day7 test <circuit> <vectors>` to run a file of test vectors against a circuit, or
+/// * `day7 table <circuit> <output> <bits> <input>...` to print the truth table of an output wire.
 fn main() {
-	println!("Processing input 1");
-	process_input("input.txt");
+	let args: Vec<String> = std::env::args().collect();
+
+	match args.get(1).map(|a| a.as_str()) {
+		None => {
+			println!("Processing input 1");
+			process_input("input.txt");
 
-	println!("Processing input 2");
-	process_input("input2.txt");
+			println!("Processing input 2");
+			process_input("input2.txt");
+		},
+		Some("test") if args.len() == 4 => test_circuit(&args[2], &args[3]),
+		Some("table") if args.len() >= 6 => print_truth_table(&args[2], &args[3], &args[4], &args[5..]),
+		_ => {
+			println!("Usage: day7");
+			println!("       day7 test <circuit> <vectors>");
+			println!("       day7 table <circuit> <output> <bits> <input>...");
+		}
+	}
 }
 
 fn process_input(file: &str) {
+	let program = match load_program(file) {
+		Some(program) => program,
+		None => return,
+	};
+
+	let instructions = program.instructions();
+	println!("Processing {:?} instructions", instructions.len());
+
+	let registers = run_instructions(instructions);
+	println!("The value in register 'a' after running the program is {:?}", registers.get(&"a").unwrap());
+}
+
+fn load_program(file: &str) -> Option<parser::Program> {
+	match parser::Program::load(file) {
+		Ok(program) => Some(program),
+		Err(e) => {
+			println!("Failed to load program: {}", e);
+			None
+		}
+	}
+}
+
+fn test_circuit(circuit: &str, vectors_file: &str) {
 	use std::fs::File;
 	use std::io::prelude::*;
 
-	let mut f = File::open(file).unwrap();
+	let program = match load_program(circuit) {
+		Some(program) => program,
+		None => return,
+	};
+
 	let mut buffer = String::new();
+	if let Err(e) = File::open(vectors_file).and_then(|mut f| f.read_to_string(&mut buffer)) {
+		println!("Failed to read test vectors: {}: {}", vectors_file, e);
+		return
+	}
 
-	f.read_to_string(&mut buffer).unwrap();
+	let vectors = match vectors::parse_vectors(vectors_file, &buffer) {
+		Ok(vectors) => vectors,
+		Err(e) => {
+			println!("Failed to parse test vectors: {}", e);
+			return
+		}
+	};
 
-	let registers = run_program(&buffer);
-	println!("The value in register 'a' after running the program is {:?}", registers.get(&"a").unwrap());
+	let mismatches = vectors::run_vectors(&program.instructions(), &vectors);
+	for mismatch in &mismatches {
+		match mismatch.actual {
+			Some(actual) => println!("{}:{}: expected {}={} but got {}",
+				vectors_file, mismatch.line, mismatch.wire, mismatch.expected, actual),
+			None => println!("{}:{}: expected {}={} but the wire has no signal",
+				vectors_file, mismatch.line, mismatch.wire, mismatch.expected),
+		}
+	}
+
+	println!("{} vectors run, {} mismatches", vectors.len(), mismatches.len());
 }
 
-/// Runs a program.
+fn print_truth_table(circuit: &str, output: &str, bits: &str, inputs: &[String]) {
+	use std::str::FromStr;
+
+	let program = match load_program(circuit) {
+		Some(program) => program,
+		None => return,
+	};
+
+	let bits = match u32::from_str(bits) {
+		Ok(bits) => bits,
+		Err(_) => {
+			println!("Failed to parse bit width: {:?}", bits);
+			return
+		}
+	};
+
+	let inputs: Vec<&str> = inputs.iter().map(|i| i.as_str()).collect();
+	match vectors::truth_table(&program.instructions(), &inputs, bits, output) {
+		Ok(table) => {
+			println!("{} | {}", inputs.join(" "), output);
+			for (signals, signal) in table {
+				let signals: Vec<String> = signals.iter().map(|s| s.to_string()).collect();
+				match signal {
+					Some(signal) => println!("{} | {}", signals.join(" "), signal),
+					None => println!("{} | -", signals.join(" ")),
+				}
+			}
+		},
+		Err(e) => println!("Failed to build truth table: {}", e),
+	}
+}
+
+/// Runs the instructions of a program, returning the signal on each wire.
 ///
 /// # Notes
 /// We need to implement dependncy resolution such that we verify that instructions are executed in 
@@ -91,16 +187,18 @@ fn process_input(file: &str) {
 /// it's a Load instruction then execute it. If it's any other instruction then check to see if the 
 /// neccesary registers are initialized, if they are not then push the instruction back on the stack,
 /// and so on until all instructions have been resolved.
-fn run_program(input: &str) -> HashMap<&str, u16> {
-	use parser::{Instruction, parse_input};
-	let mut instructions = parse_input(input);
+///
+/// Instructions whose inputs are never provided a signal can't be resolved, so once every remaining
+/// instruction has been put back without progress they are left out of the result.
+fn run_instructions<'a>(mut instructions: Vec<parser::Instruction<'a>>) -> HashMap<&'a str, u16> {
+	use parser::Instruction;
 	instructions.reverse();
 
 	let mut registers = HashMap::new();
+	let mut stalled = 0;
 
-	println!("Processing {:?} instructions", instructions.len());
-
-	while instructions.len() > 0 {
+	while stalled < instructions.len() {
+		let remaining = instructions.len();
 		let instruction = instructions.pop().unwrap();
 
 		match instruction {
@@ -227,13 +325,19 @@ fn run_program(input: &str) -> HashMap<&str, u16> {
 				}
 			},
 		}
+
+		if instructions.len() == remaining {
+			stalled += 1;
+		} else {
+			stalled = 0;
+		}
 	}
 
 	registers
 }
 
 #[test]
-fn run_program_test() {
+fn run_instructions_test() {
 	let program = 
 		r"123 -> x
 			456 -> y
@@ -255,7 +359,8 @@ fn run_program_test() {
 
 	println!("Running program {}", program);
 
-	assert_eq!(expect, run_program(program));
+	let loaded = parser::Program::parse("test.txt", program).unwrap();
+	assert_eq!(expect, run_instructions(loaded.instructions()));
 
 	let program = 
 		r"b RSHIFT 5 -> f
@@ -269,5 +374,18 @@ fn run_program_test() {
 	expect.insert("h", (44430 >> 3) & (44430 >> 5));
 
 	println!("Running program {}", program);
-	assert_eq!(expect, run_program(program));
+	let loaded = parser::Program::parse("test.txt", program).unwrap();
+	assert_eq!(expect, run_instructions(loaded.instructions()));
+
+	let program = 
+		r"123 -> x
+		x AND y -> d
+		NOT x -> h";
+	let mut expect = HashMap::new();
+	expect.insert("x", 123);
+	expect.insert("h", 65412);
+
+	println!("Running program {}", program);
+	let loaded = parser::Program::parse("test.txt", program).unwrap();
+	assert_eq!(expect, run_instructions(loaded.instructions()));
 }
\ No newline at end of file
@@ -1,4 +1,14 @@
-/// Provides a parser for the Advent Assembly Language.
+//! Provides a parser for the Advent Assembly Language.
+//!
+//! A program is a list of lines, each holding at most one instruction. Anything following a `#` 
+//! is a comment, and blank lines are ignored. A line of the form `include "file"` pulls in the 
+//! instructions of another program, resolved relative to the directory of the including file.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
 
 /// A Token represents a gramattical token in a program.
 /// 
@@ -32,30 +42,335 @@ pub enum Instruction<'a> {
 	RShift(&'a str, &'a str, u16),
 }
 
-pub fn parse_input(input: &str) -> Vec<Instruction> {
-	let tokens = tokenize_input(input);
+impl<'a> Instruction<'a> {
+	/// Returns the wire that this instruction provides a signal to.
+	pub fn destination(&self) -> &'a str {
+		match *self {
+			Instruction::And(d, _, _) => d,
+			Instruction::AndWC(d, _, _) => d,
+			Instruction::AndCW(d, _, _) => d,
+			Instruction::Load(d, _) => d,
+			Instruction::LoadW(d, _) => d,
+			Instruction::LShift(d, _, _) => d,
+			Instruction::Not(d, _) => d,
+			Instruction::Or(d, _, _) => d,
+			Instruction::OrWC(d, _, _) => d,
+			Instruction::OrCW(d, _, _) => d,
+			Instruction::RShift(d, _, _) => d,
+		}
+	}
+}
 
-	let mut instructions = Vec::new();
+/// A ParseError reports a problem with a program along with the file and line it occurred on.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+	pub file: String,
+	pub line: usize,
+	pub message: String,
+}
 
-	let mut start = 0;
-	while start < tokens.len() {
-		match parse_expression(&tokens[start..]) {
-			Some((instruction, tokens_consumed)) => {
-				instructions.push(instruction);
-				start = start + tokens_consumed;
-			},
-			None => {
-				println!("Failed to parse tokens: {:?}", &tokens[start..]);
-				break
+impl fmt::Display for ParseError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}:{}: {}", self.file, self.line, self.message)
+	}
+}
+
+/// A Line is the meaning of a single line of program text.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Line<'a> {
+	Empty,
+	Include(&'a str),
+	Instruction(Instruction<'a>),
+}
+
+/// A Program holds the text of a circuit file together with the text of every file it includes.
+///
+/// Each file is loaded at most once, so a sub-circuit shared by several files is only wired up a 
+/// single time. Including a file from itself, directly or indirectly, is an error.
+pub struct Program {
+	sources: Vec<String>,
+}
+
+impl Program {
+	/// Loads the program in `path` and every file it includes, checking each line as it goes.
+	pub fn load<P: AsRef<Path>>(path: P) -> Result<Program, ParseError> {
+		let mut loader = Loader::default();
+		try_load(&mut loader, path.as_ref(), None)?;
+
+		Ok(Program { sources: loader.sources })
+	}
+
+	/// Parses `text` as the contents of the program in `file`, checking each line as it goes and 
+	/// loading every file it includes. Tests use this to run programs without writing them out.
+	#[cfg(test)]
+	pub fn parse(file: &str, text: &str) -> Result<Program, ParseError> {
+		let mut loader = Loader::default();
+		try_parse(&mut loader, Path::new(file), text.to_string())?;
+
+		Ok(Program { sources: loader.sources })
+	}
+
+	/// Returns the instructions of every file in the program.
+	pub fn instructions<'a>(&'a self) -> Vec<Instruction<'a>> {
+		let mut instructions = Vec::new();
+
+		for source in &self.sources {
+			for line in source.lines() {
+				// Every line was checked when the program was loaded.
+				if let Ok(Line::Instruction(instruction)) = parse_line(line) {
+					instructions.push(instruction);
+				}
 			}
 		}
+
+		instructions
+	}
+}
+
+/// The state carried through the recursive loading of a program.
+#[derive(Default)]
+struct Loader {
+	sources: Vec<String>,
+	/// The canonical path of every file loaded so far.
+	loaded: Vec<PathBuf>,
+	/// The canonical paths of the files currently being loaded, outermost first.
+	stack: Vec<PathBuf>,
+	/// Where each wire's signal is provided, as `file:line`.
+	drivers: HashMap<String, String>,
+}
+
+/// Loads `path` into `loader`, along with anything it includes.
+///
+/// `site` is the file name and line number of the `include` that asked for `path`, which is where 
+/// any problem with opening the file gets reported.
+fn try_load(loader: &mut Loader, path: &Path, site: Option<(&str, usize)>) -> Result<(), ParseError> {
+	let name = path.display().to_string();
+	let error_at = |message: String| {
+		let (file, line) = site.unwrap_or((&name, 0));
+		ParseError { file: file.to_string(), line, message }
+	};
+
+	let canonical = match path.canonicalize() {
+		Ok(canonical) => canonical,
+		Err(e) => return Err(error_at(format!("cannot open {}: {}", name, e))),
+	};
+
+	if let Some(start) = loader.stack.iter().position(|p| *p == canonical) {
+		let mut cycle: Vec<String> = loader.stack[start..].iter()
+			.map(|p| p.display().to_string())
+			.collect();
+		cycle.push(canonical.display().to_string());
+		return Err(error_at(format!("include cycle: {}", cycle.join(" -> "))));
+	}
+
+	if loader.loaded.contains(&canonical) {
+		return Ok(());
+	}
+
+	let mut text = String::new();
+	if let Err(e) = File::open(path).and_then(|mut f| f.read_to_string(&mut text)) {
+		return Err(error_at(format!("cannot read {}: {}", name, e)));
+	}
+
+	loader.loaded.push(canonical.clone());
+	loader.stack.push(canonical);
+	try_parse(loader, path, text)?;
+	loader.stack.pop();
+
+	Ok(())
+}
+
+/// Checks each line of `text`, the contents of `path`, and loads everything it includes into 
+/// `loader` before the text itself.
+fn try_parse(loader: &mut Loader, path: &Path, text: String) -> Result<(), ParseError> {
+	let name = path.display().to_string();
+	let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+	for (i, line) in text.lines().enumerate() {
+		let line_number = i + 1;
+		let located = |message: String| {
+			ParseError { file: name.clone(), line: line_number, message }
+		};
+
+		match parse_line(line).map_err(&located)? {
+			Line::Empty => {},
+			Line::Include(file) => {
+				try_load(loader, &dir.join(file), Some((&name, line_number)))?;
+			},
+			Line::Instruction(instruction) => {
+				let wire = instruction.destination().to_string();
+				let here = format!("{}:{}", name, line_number);
+				if let Some(previous) = loader.drivers.insert(wire.clone(), here) {
+					return Err(located(format!("wire `{}` is already provided a signal at {}", 
+						wire, previous)));
+				}
+			},
+		}
+	}
+
+	loader.sources.push(text);
+
+	Ok(())
+}
+
+/// Parses a single line of program text.
+fn parse_line<'a>(line: &'a str) -> Result<Line<'a>, String> {
+	let line = strip_comment(line).trim();
+
+	if line.is_empty() {
+		return Ok(Line::Empty);
+	}
+
+	if let Some(rest) = line.strip_prefix("include") {
+		let rest = rest.trim();
+		// A wire may be called `include`, so only treat the line as a directive when a quoted 
+		// file name follows.
+		if rest.starts_with('"') {
+			return if rest.len() > 1 && rest.ends_with('"') && !rest[1..rest.len() - 1].contains('"') {
+				Ok(Line::Include(&rest[1..rest.len() - 1]))
+			} else {
+				Err(format!("malformed include `{}`, expected include \"file\"", line))
+			};
+		}
 	}
 
-	instructions
+	let tokens = tokenize_input(line)?;
+
+	// The parse functions expect every token of an instruction to be present, so check the 
+	// shape of the line before handing it over.
+	let expected_len = match (tokens.first(), tokens.get(1)) {
+		(Some(&Token::Not), _) => 4,
+		(_, Some(&Token::Assign)) => 3,
+		(_, Some(_)) => 5,
+		_ => 0,
+	};
+
+	if tokens.len() == expected_len {
+		if let Some((instruction, _)) = parse_expression(&tokens) {
+			return Ok(Line::Instruction(instruction));
+		}
+	}
+
+	Err(format!("`{}` is not a valid instruction", line))
 }
 
 #[test]
-fn parse_input_test() {
+fn parse_line_test() {
+	let test_cases = vec!(
+		("", Ok(Line::Empty)),
+		("   \t", Ok(Line::Empty)),
+		("# a comment", Ok(Line::Empty)),
+		("123 -> x # load x", Ok(Line::Instruction(Instruction::Load("x", 123)))),
+		("include \"adder.txt\"", Ok(Line::Include("adder.txt"))),
+		("include \"adder.txt\" # shared", Ok(Line::Include("adder.txt"))),
+		("include -> x", Ok(Line::Instruction(Instruction::LoadW("x", "include")))),
+		("include \"adder.txt", Err("malformed include `include \"adder.txt`, expected include \"file\"".to_string())),
+		("x AND y", Err("`x AND y` is not a valid instruction".to_string())),
+		("1 AND", Err("`1 AND` is not a valid instruction".to_string())),
+		("NOT x", Err("`NOT x` is not a valid instruction".to_string())),
+		("x", Err("`x` is not a valid instruction".to_string())),
+		("70000 -> x", Err("constant `70000` does not fit in 16 bits".to_string())),
+	);
+
+	for (i, test_case) in test_cases.iter().enumerate() {
+		let (input, ref expect) = *test_case;
+
+		println!("Test case #{:?}: input = {:?} expect = {:?}", i, input, expect);
+		assert_eq!(*expect, parse_line(input));
+	}
+}
+
+/// A TestDir is a directory of files written for a test, which is removed when it is dropped.
+#[cfg(test)]
+struct TestDir(PathBuf);
+
+#[cfg(test)]
+impl Drop for TestDir {
+	fn drop(&mut self) {
+		let _ = ::std::fs::remove_dir_all(&self.0);
+	}
+}
+
+/// Writes `files` into a directory of its own for a test, unique to this run of the tests.
+#[cfg(test)]
+fn write_test_files(test: &str, files: &[(&str, &str)]) -> TestDir {
+	use std::fs;
+	use std::sync::atomic::{AtomicUsize, Ordering};
+
+	static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+	let unique = format!("day7_{}_{}_{}", test, ::std::process::id(), COUNTER.fetch_add(1, Ordering::SeqCst));
+	let dir = TestDir(::std::env::temp_dir().join(unique));
+	fs::create_dir_all(&dir.0).unwrap();
+
+	for &(name, contents) in files {
+		let path = dir.0.join(name);
+		fs::create_dir_all(path.parent().unwrap()).unwrap();
+		let mut f = File::create(path).unwrap();
+		f.write_all(contents.as_bytes()).unwrap();
+	}
+
+	dir
+}
+
+#[test]
+fn program_load_test() {
+	let dir = write_test_files("program_load", &[
+		("main.txt", "# Top level circuit\n\ninclude \"inputs.txt\"\ninclude \"gates/and.txt\"\n"),
+		("inputs.txt", "123 -> x # first input\n456 -> y\n"),
+		("gates/and.txt", "include \"../inputs.txt\"\nx AND y -> d\n"),
+	]);
+
+	let program = Program::load(dir.0.join("main.txt")).unwrap();
+	let expect = vec!(
+		Instruction::Load("x", 123),
+		Instruction::Load("y", 456),
+		Instruction::And("d", "x", "y"),
+	);
+
+	assert_eq!(expect, program.instructions());
+
+	// A parsed program includes files relative to the file it is named as.
+	let main = dir.0.join("main.txt").display().to_string();
+	let program = Program::parse(&main, "include \"gates/and.txt\"\nNOT d -> e").unwrap();
+	let expect = vec!(
+		Instruction::Load("x", 123),
+		Instruction::Load("y", 456),
+		Instruction::And("d", "x", "y"),
+		Instruction::Not("e", "d"),
+	);
+	assert_eq!(expect, program.instructions());
+}
+
+#[test]
+fn program_load_error_test() {
+	let dir = write_test_files("program_load_error", &[
+		("bad.txt", "123 -> x\n\nx FOO y -> z\n"),
+		("a.txt", "1 -> a\ninclude \"b.txt\"\n"),
+		("b.txt", "\n# b includes a\ninclude \"a.txt\"\n"),
+		("missing.txt", "include \"nowhere.txt\"\n"),
+		("twice.txt", "1 -> a\ninclude \"a.txt\"\n"),
+	]);
+	let name = |file: &str| dir.0.join(file).display().to_string();
+
+	let error = Program::load(dir.0.join("bad.txt")).err().unwrap();
+	assert_eq!((name("bad.txt"), 3), (error.file, error.line));
+	assert_eq!("`x FOO y -> z` is not a valid instruction", error.message);
+
+	let error = Program::load(dir.0.join("a.txt")).err().unwrap();
+	assert_eq!((name("b.txt"), 3), (error.file, error.line));
+	assert!(error.message.starts_with("include cycle: "));
+
+	let error = Program::load(dir.0.join("missing.txt")).err().unwrap();
+	assert_eq!((name("missing.txt"), 1), (error.file, error.line));
+	assert!(error.message.starts_with("cannot open "));
+
+	let error = Program::load(dir.0.join("twice.txt")).err().unwrap();
+	assert_eq!((name("a.txt"), 1), (error.file, error.line));
+	assert_eq!(format!("wire `a` is already provided a signal at {}:1", name("twice.txt")), error.message);
+}
+
+#[test]
+fn program_instructions_test() {
 	let test_cases = vec!(
 		("123 -> x", vec!(Instruction::Load("x", 123))),
 		("123 -> x\n456 -> y", vec!(Instruction::Load("x", 123), Instruction::Load("y", 456))),
@@ -98,8 +413,17 @@ fn parse_input_test() {
 		let (input, ref expect) = *test_case;
 
 		println!("Test case #{:?}: input = {:?} expect = {:?}", i, input, expect);
-		assert_eq!(*expect, parse_input(input));
+		let program = Program::parse("test.txt", input).unwrap();
+		assert_eq!(*expect, program.instructions());
 	}
+
+	let error = Program::parse("test.txt", "1 -> a\n\na AND 1 -> a").err().unwrap();
+	let expect = ParseError { 
+		file: "test.txt".to_string(), 
+		line: 3, 
+		message: "wire `a` is already provided a signal at test.txt:1".to_string(),
+	};
+	assert_eq!(expect, error);
 }
 
 fn parse_expression<'a>(tokens: &[Token<'a>]) -> Option<(Instruction<'a>, usize)> {
@@ -318,14 +642,24 @@ fn parse_expression_test() {
 	assert_eq!(3, tokens_consumed);
 }
                                                                         
-fn tokenize_input(input: &str) -> Vec<Token> {
-	use std::str::FromStr;											   
-                                                                       
-	let mut tokens: Vec<Token> = Vec::new();						   
-	                                                                   
-	let sub_strings: Vec<&str> = input.split_whitespace().collect();   
-                                                                       
-	for sub_string in sub_strings {                                    
+/// Removes a trailing `#` comment from a line of program text.
+fn strip_comment(line: &str) -> &str {
+	match line.find('#') {
+		Some(i) => &line[..i],
+		None => line,
+	}
+}
+
+fn tokenize_input<'a>(input: &'a str) -> Result<Vec<Token<'a>>, String> {
+	use std::str::FromStr;
+
+	let mut tokens: Vec<Token> = Vec::new();
+
+	let sub_strings: Vec<&str> = input.lines()
+		.flat_map(|line| strip_comment(line).split_whitespace())
+		.collect();
+
+	for sub_string in sub_strings {
 		match sub_string {
 			"->" => {
 				tokens.push(Token::Assign);
@@ -345,17 +679,18 @@ fn tokenize_input(input: &str) -> Vec<Token> {
 			"NOT" => {
 				tokens.push(Token::Not);
 			},
-			s => if s.chars().all(|c| c.is_digit(10)) {
-				let constant = u16::from_str(s).unwrap();
-				tokens.push(Token::Constant(constant));
+			s => if s.chars().all(|c| c.is_ascii_digit()) {
+				match u16::from_str(s) {
+					Ok(constant) => tokens.push(Token::Constant(constant)),
+					Err(_) => return Err(format!("constant `{}` does not fit in 16 bits", s)),
+				}
 			} else {
-				let wire_name: &str = s.clone();
-				tokens.push(Token::Wire(wire_name));
+				tokens.push(Token::Wire(s));
 			},
 		}
 	}
 
-	tokens
+	Ok(tokens)
 }
 
 #[test]
@@ -377,6 +712,13 @@ fn tokenize_input_test() {
 		println!("Test case #{:?}: input = {:?} expect = {:?}", 
 			i, input, expect);
 
-		assert_eq!(*expect, tokenize_input(input));
+		assert_eq!(Ok(expect.clone()), tokenize_input(input));
 	}
+
+	let input = "123 -> x # load x\n\n# NOT x -> y\n456 -> y";
+	let expect = vec!(
+		Token::Constant(123), Token::Assign, Token::Wire("x"),
+		Token::Constant(456), Token::Assign, Token::Wire("y"),
+	);
+	assert_eq!(Ok(expect), tokenize_input(input));
 }
\ No newline at end of file
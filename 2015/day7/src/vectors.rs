@@ -0,0 +1,216 @@
+//! Provides test vectors and truth tables for checking circuits.
+//!
+//! A test vector file holds one vector per line. Each vector lists the signals to force onto input
+//! wires, then `=>`, then the signals expected on output wires:
+//!
+//!     # x and y in, d and e out
+//!     x=123 y=456 => d=72 e=507
+//!
+//! As with programs, anything following a `#` is a comment and blank lines are ignored.
+
+use parser::{Instruction, ParseError};
+
+/// A TestVector is a set of input signals and the output signals they should produce.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TestVector<'a> {
+	/// The line of the vector file this vector came from.
+	pub line: usize,
+	pub inputs: Vec<(&'a str, u16)>,
+	pub outputs: Vec<(&'a str, u16)>,
+}
+
+/// A Mismatch is an output wire that did not carry the signal a test vector expected.
+///
+/// `actual` is `None` when the circuit never provided the wire with a signal.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mismatch<'a> {
+	pub line: usize,
+	pub wire: &'a str,
+	pub expected: u16,
+	pub actual: Option<u16>,
+}
+
+/// Parses the test vectors in `input`, naming `file` in any error.
+pub fn parse_vectors<'a>(file: &str, input: &'a str) -> Result<Vec<TestVector<'a>>, ParseError> {
+	let mut vectors = Vec::new();
+
+	for (i, line) in input.lines().enumerate() {
+		let error = |message: String| {
+			ParseError { file: file.to_string(), line: i + 1, message }
+		};
+
+		let text = match line.find('#') {
+			Some(j) => &line[..j],
+			None => line,
+		}.trim();
+
+		if text.is_empty() {
+			continue;
+		}
+
+		let mut sides = text.split("=>");
+		let (inputs, outputs) = match (sides.next(), sides.next(), sides.next()) {
+			(Some(inputs), Some(outputs), None) => (inputs, outputs),
+			_ => return Err(error(format!("`{}` should have the form `inputs => outputs`", text))),
+		};
+
+		vectors.push(TestVector {
+			line: i + 1,
+			inputs: parse_assignments(inputs).map_err(&error)?,
+			outputs: parse_assignments(outputs).map_err(&error)?,
+		});
+	}
+
+	Ok(vectors)
+}
+
+/// Parses a whitespace separated list of `wire=signal` assignments.
+fn parse_assignments(input: &str) -> Result<Vec<(&str, u16)>, String> {
+	use std::str::FromStr;
+
+	let mut assignments = Vec::new();
+
+	for assignment in input.split_whitespace() {
+		let mut parts = assignment.split('=');
+		match (parts.next(), parts.next().map(u16::from_str), parts.next()) {
+			(Some(wire), Some(Ok(signal)), None) if !wire.is_empty() => {
+				assignments.push((wire, signal));
+			},
+			_ => return Err(format!("`{}` should have the form `wire=signal`", assignment)),
+		}
+	}
+
+	Ok(assignments)
+}
+
+#[test]
+fn parse_vectors_test() {
+	let input = "# A comment\n\nx=123 y=456 => d=72 # trailing\n=> a=1\n";
+	let expect = vec!(
+		TestVector { line: 3, inputs: vec!(("x", 123), ("y", 456)), outputs: vec!(("d", 72)) },
+		TestVector { line: 4, inputs: vec!(), outputs: vec!(("a", 1)) },
+	);
+	assert_eq!(Ok(expect), parse_vectors("test.vec", input));
+
+	let error = parse_vectors("test.vec", "x=1 => d=1\nx=1 d=1").err().unwrap();
+	assert_eq!(("test.vec".to_string(), 2), (error.file, error.line));
+
+	let error = parse_vectors("test.vec", "x=70000 => d=1").err().unwrap();
+	assert_eq!("`x=70000` should have the form `wire=signal`", error.message);
+}
+
+/// Returns `instructions` with each of `inputs` forced onto its wire.
+///
+/// Whatever the circuit would otherwise provide to an input wire is replaced, and input wires that
+/// nothing provides a signal to are added.
+pub fn override_inputs<'a>(instructions: &[Instruction<'a>], inputs: &[(&'a str, u16)])
+	-> Vec<Instruction<'a>> {
+
+	let mut overridden: Vec<Instruction<'a>> = instructions.iter()
+		.filter(|i| !inputs.iter().any(|&(wire, _)| wire == i.destination()))
+		.cloned()
+		.collect();
+
+	for &(wire, signal) in inputs {
+		overridden.push(Instruction::Load(wire, signal));
+	}
+
+	overridden
+}
+
+/// Runs every vector against the circuit and returns the outputs that did not match.
+pub fn run_vectors<'a>(instructions: &[Instruction<'a>], vectors: &[TestVector<'a>])
+	-> Vec<Mismatch<'a>> {
+
+	let mut mismatches = Vec::new();
+
+	for vector in vectors {
+		let registers = ::run_instructions(override_inputs(instructions, &vector.inputs));
+
+		for &(wire, expected) in &vector.outputs {
+			let actual = registers.get(wire).cloned();
+			if actual != Some(expected) {
+				mismatches.push(Mismatch { line: vector.line, wire, expected, actual });
+			}
+		}
+	}
+
+	mismatches
+}
+
+#[test]
+fn run_vectors_test() {
+	let program = ::parser::Program::parse("test.txt", "x AND y -> d\nx OR y -> e\nNOT x -> h\n1 -> x").unwrap();
+	let circuit = program.instructions();
+	let vectors = parse_vectors("test.vec",
+		"x=123 y=456 => d=72 e=507\ny=0 => h=65534 e=2\nx=1 => f=1").unwrap();
+
+	let expect = vec!(
+		Mismatch { line: 2, wire: "e", expected: 2, actual: Some(1) },
+		Mismatch { line: 3, wire: "f", expected: 1, actual: None },
+	);
+	assert_eq!(expect, run_vectors(&circuit, &vectors));
+}
+
+/// The most input bits a truth table may enumerate.
+pub const MAX_TRUTH_TABLE_BITS: u32 = 16;
+
+/// A row of a truth table: the signal on each input wire, and the resulting output signal.
+pub type TruthTableRow = (Vec<u16>, Option<u16>);
+
+/// Builds the truth table of `output` over every combination of signals on `inputs`.
+///
+/// Each input takes every value that fits in `bits` bits, and the output is truncated to the same
+/// width, so a one bit table of `NOT x -> y` reads `0 -> 1`, `1 -> 0`. Rows are ordered with the
+/// first input as the most significant. An output that is never provided a signal is `None`.
+pub fn truth_table<'a>(instructions: &[Instruction<'a>], inputs: &[&'a str], bits: u32, output: &str)
+	-> Result<Vec<TruthTableRow>, String> {
+
+	if bits == 0 || bits > 16 {
+		return Err(format!("signals are between 1 and 16 bits wide, not {}", bits));
+	}
+
+	let total_bits = bits * inputs.len() as u32;
+	if total_bits > MAX_TRUTH_TABLE_BITS {
+		return Err(format!("{} inputs of {} bits is too many, at most {} input bits are supported",
+			inputs.len(), bits, MAX_TRUTH_TABLE_BITS));
+	}
+
+	let mask = ((1u32 << bits) - 1) as u16;
+	let mut table = Vec::new();
+
+	for row in 0..(1u32 << total_bits) {
+		let signals: Vec<u16> = (0..inputs.len())
+			.map(|i| ((row >> (bits * (inputs.len() - 1 - i) as u32)) as u16) & mask)
+			.collect();
+
+		let assignments: Vec<(&'a str, u16)> = inputs.iter().cloned().zip(signals.iter().cloned()).collect();
+		let registers = ::run_instructions(override_inputs(instructions, &assignments));
+
+		table.push((signals, registers.get(output).map(|signal| signal & mask)));
+	}
+
+	Ok(table)
+}
+
+#[test]
+fn truth_table_test() {
+	let program = ::parser::Program::parse("test.txt", "x AND y -> d\nx OR y -> e\nNOT x -> h").unwrap();
+	let circuit = program.instructions();
+
+	let expect = vec!(
+		(vec!(0, 0), Some(0)),
+		(vec!(0, 1), Some(0)),
+		(vec!(1, 0), Some(0)),
+		(vec!(1, 1), Some(1)),
+	);
+	assert_eq!(Ok(expect), truth_table(&circuit, &["x", "y"], 1, "d"));
+
+	let expect = vec!((vec!(0), Some(3)), (vec!(1), Some(2)), (vec!(2), Some(1)), (vec!(3), Some(0)));
+	assert_eq!(Ok(expect), truth_table(&circuit, &["x"], 2, "h"));
+
+	let expect = vec!((vec!(0), None), (vec!(1), None));
+	assert_eq!(Ok(expect), truth_table(&circuit, &["x"], 1, "e"));
+
+	assert!(truth_table(&circuit, &["x", "y"], 9, "d").is_err());
+}